@@ -0,0 +1,42 @@
+extern crate amethyst;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process;
+
+// Shares the format with the game's `PackStore`, which is the only part of the
+// module this binary doesn't use.
+#[allow(dead_code)]
+#[path = "../stores/pack.rs"]
+mod pack;
+
+fn main() {
+    let args = env::args().collect::<Vec<_>>();
+
+    if args.len() != 3 {
+        let _ = writeln!(
+            io::stderr(),
+            "Usage: {} <asset directory> <output pack>",
+            args.get(0).map(|s| &s[..]).unwrap_or("pack")
+        );
+        process::exit(1);
+    }
+
+    let result = File::create(&args[2])
+        .map(BufWriter::new)
+        .and_then(|mut out| {
+            let count = pack::write_pack(&args[1], &mut out)?;
+            out.flush()?;
+
+            Ok(count)
+        });
+
+    match result {
+        Ok(count) => println!("Packed {} files into {}", count, args[2]),
+        Err(e)    => {
+            let _ = writeln!(io::stderr(), "Cannot write {}: {}", args[2], e);
+            process::exit(1);
+        },
+    }
+}
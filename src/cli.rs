@@ -0,0 +1,151 @@
+use std::env;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+use std::str::FromStr;
+
+const USAGE: &'static str = "\
+Options:
+    --assets <dir>         Read assets from <dir> instead of resources/assets
+    --pack <file>          Read assets from a pack under the root, repeatable
+    --overlay <dir>        Read assets from <dir> over the root, repeatable
+    --config <file>        Use <file> instead of resources/config.yml
+    --level <map>          Hunt on <map>, relative to the asset root
+    --windowed             Ignore the fullscreen setting for this run
+    --fullscreen           Go fullscreen for this run
+    --resolution <w>x<h>   Ignore the resolution setting for this run
+    --seed <n>             Seed the random number generator
+    --record <file>        Save keyboard input to <file>
+    --replay <file>        Play back input saved with --record
+    --headless             Hunt without a window, at a fixed 60 frames a second
+    --frames <n>           Go straight into a hunt and quit after <n> frames
+    --help                 Show this message";
+
+// Everything that can be set from the command line. Anything left as `None`
+// falls back to config.yml, and then to the defaults.
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    pub assets:     Option<PathBuf>,
+    pub packs:      Vec<PathBuf>,
+    pub overlays:   Vec<PathBuf>,
+    pub config:     Option<PathBuf>,
+    pub level:      Option<String>,
+    pub fullscreen: Option<bool>,
+    pub resolution: Option<[u32; 2]>,
+    pub seed:       Option<u64>,
+    pub record:     Option<PathBuf>,
+    pub replay:     Option<PathBuf>,
+    pub headless:   bool,
+    pub frames:     Option<u64>,
+}
+
+impl Options {
+    // Parses the process's arguments, exiting with the usage on anything it
+    // doesn't understand
+    pub fn from_args() -> Self {
+        let args = env::args().collect::<Vec<_>>();
+
+        let (program, args) = match args.split_first() {
+            Some((program, args)) => (&program[..], args),
+            None                  => ("hunting_game", &[][..]),
+        };
+
+        if args.iter().any(|a| a == "--help" || a == "-h") {
+            println!("Usage: {} [options]\n\n{}", program, USAGE);
+            process::exit(0);
+        }
+
+        match Self::parse(args) {
+            Ok(options) => options,
+            Err(e)      => {
+                let _ = writeln!(
+                    io::stderr(),
+                    "{}\n\nUsage: {} [options]\n\n{}",
+                    e,
+                    program,
+                    USAGE
+                );
+                process::exit(1);
+            },
+        }
+    }
+
+    // `args` doesn't include the program name
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Options::default();
+        let mut args    = args.iter();
+
+        while let Some(flag) = args.next() {
+            let flag = &flag[..];
+
+            match flag {
+                "--assets" =>
+                    options.assets = Some(value(flag, &mut args)?.into()),
+                "--pack" =>
+                    options.packs.push(value(flag, &mut args)?.into()),
+                "--overlay" =>
+                    options.overlays.push(value(flag, &mut args)?.into()),
+                "--config" =>
+                    options.config = Some(value(flag, &mut args)?.into()),
+                "--level" =>
+                    options.level = Some(value(flag, &mut args)?.into()),
+                "--windowed" =>
+                    options.fullscreen = Some(false),
+                "--fullscreen" =>
+                    options.fullscreen = Some(true),
+                "--resolution" => {
+                    let value = value(flag, &mut args)?;
+
+                    options.resolution = Some(resolution(value).ok_or_else(
+                        || format!("Invalid resolution {}", value)
+                    )?);
+                },
+                "--seed" =>
+                    options.seed = Some(number(flag, &mut args)?),
+                "--record" =>
+                    options.record = Some(value(flag, &mut args)?.into()),
+                "--replay" =>
+                    options.replay = Some(value(flag, &mut args)?.into()),
+                "--headless" =>
+                    options.headless = true,
+                "--frames" =>
+                    options.frames = Some(number(flag, &mut args)?),
+                _ => return Err(format!("Unknown option {}", flag)),
+            }
+        }
+
+        if options.record.is_some() && options.replay.is_some() {
+            return Err("--record and --replay can't be used together".into());
+        }
+
+        Ok(options)
+    }
+}
+
+fn value<'a, I>(flag: &str, args: &mut I) -> Result<&'a str, String>
+    where I: Iterator<Item=&'a String>
+{
+    args.next()
+        .map(|v| &v[..])
+        .ok_or_else(|| format!("{} needs a value", flag))
+}
+
+fn number<'a, I, N>(flag: &str, args: &mut I) -> Result<N, String>
+    where I: Iterator<Item=&'a String>,
+          N: FromStr,
+{
+    let value = value(flag, args)?;
+
+    value.parse()
+        .map_err(|_| format!("{} needs a number, not {}", flag, value))
+}
+
+// `<width>x<height>`, neither of which can be zero
+fn resolution(value: &str) -> Option<[u32; 2]> {
+    let mut parts = value.splitn(2, 'x').map(str::parse::<u32>);
+
+    match (parts.next(), parts.next()) {
+        (Some(Ok(w)), Some(Ok(h))) if w > 0 && h > 0 => Some([w, h]),
+        _                                           => None,
+    }
+}
@@ -0,0 +1,328 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use amethyst::context::asset_manager::{
+    AssetId,
+    AssetLoader,
+    AssetManager,
+    AssetStore,
+};
+
+use stores::AssetPaths;
+use super::{error, find_cycle, parse_file, visit_file, VisitFile};
+use super::{Dependencies, LoaderError, Parse};
+
+// The GPU half of a load: a loader that has already been parsed on a worker
+// thread, waiting for the main thread to hand it to the asset manager.
+trait Pending: Send {
+    fn upload(
+        self: Box<Self>,
+        assets: &mut AssetManager,
+    ) -> Result<AssetId, LoaderError>;
+}
+
+struct Loaded<A, L> {
+    name:   String,
+    loader: L,
+    _asset: PhantomData<fn() -> A>,
+}
+
+impl<A, L> Pending for Loaded<A, L>
+    where A: Any + Send + Sync,
+          L: AssetLoader<A> + Send
+{
+    fn upload(
+        self: Box<Self>,
+        assets: &mut AssetManager,
+    ) -> Result<AssetId, LoaderError> {
+        let this = *self;
+
+        error::take_last_error();
+
+        assets.load_asset_from_data::<A, L>(&this.name, this.loader)
+            .ok_or_else(
+                || error::take_last_error().unwrap_or_else(
+                    || LoaderError::Upload(this.name.clone())
+                )
+            )
+    }
+}
+
+// The CPU half, run on a worker thread
+trait Job: Send {
+    fn run(self: Box<Self>, store: &AssetStore) -> Parsed;
+}
+
+type Parsed = Result<(Box<Pending>, Vec<String>), LoaderError>;
+
+struct ParseJob<A, L> {
+    name:   String,
+    ext:    String,
+    _types: PhantomData<fn() -> (A, L)>,
+}
+
+impl<A, L> Job for ParseJob<A, L>
+    where A: Any + Send + Sync,
+          L: AssetLoader<A> + Parse + Dependencies + Send + 'static
+{
+    fn run(self: Box<Self>, store: &AssetStore) -> Parsed {
+        let loader = parse_file::<L>(store, &self.name, &self.ext)?;
+        let deps   = loader.dependencies();
+
+        let pending: Box<Pending> = Box::new(Loaded::<A, L> {
+            name:   self.name,
+            loader: loader,
+            _asset: PhantomData,
+        });
+
+        Ok((pending, deps))
+    }
+}
+
+fn panicked(payload: Box<Any + Send>) -> LoaderError {
+    let message = payload.downcast_ref::<&str>().map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown error".into());
+
+    LoaderError::parse(None, format!("loader panicked: {}", message))
+}
+
+enum Slot {
+    Queued,
+    Parsed(Box<Pending>),
+    Uploaded,
+    Failed,
+}
+
+struct Entry {
+    file: String,
+    slot: Slot,
+    deps: Vec<usize>,
+}
+
+// Decodes images and parses models on a small pool of worker threads. Each
+// parsed file declares its dependencies, which are queued in turn, and nothing
+// is handed to the asset manager until everything it depends on has been.
+pub struct BackgroundLoader {
+    jobs:    Sender<(usize, Box<Job>)>,
+    done:    Receiver<(usize, Parsed)>,
+    entries: Vec<Entry>,
+    index:   HashMap<String, usize>,
+    errors:  Vec<(String, LoaderError)>,
+}
+
+impl BackgroundLoader {
+    pub fn new(paths: AssetPaths, threads: usize) -> Self {
+        let (job_tx, job_rx)   = mpsc::channel::<(usize, Box<Job>)>();
+        let (done_tx, done_rx) = mpsc::channel::<(usize, Parsed)>();
+        let job_rx             = Arc::new(Mutex::new(job_rx));
+
+        for _ in 0..::std::cmp::max(threads, 1) {
+            let paths   = paths.clone();
+            let job_rx  = job_rx.clone();
+            let done_tx = done_tx.clone();
+
+            thread::spawn(move || {
+                let store = paths.store();
+
+                loop {
+                    // Jobs run outside the lock, so a poisoned one still holds
+                    // a usable receiver
+                    let next = match job_rx.lock() {
+                        Ok(rx)        => rx.recv(),
+                        Err(poisoned) => poisoned.into_inner().recv(),
+                    };
+
+                    let (index, job) = match next {
+                        Ok(job) => job,
+                        Err(_)  => break,
+                    };
+
+                    // Decoders can panic on malformed files, which has to be
+                    // reported or the slot would stay queued forever
+                    let parsed = panic::catch_unwind(
+                        AssertUnwindSafe(|| job.run(&store))
+                    ).unwrap_or_else(|payload| Err(panicked(payload)));
+
+                    if done_tx.send((index, parsed)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        BackgroundLoader {
+            jobs:    job_tx,
+            done:    done_rx,
+            entries: vec![],
+            index:   HashMap::new(),
+            errors:  vec![],
+        }
+    }
+
+    pub fn queue<A, L>(&mut self, name: &str, ext: &str) -> usize
+        where A: Any + Send + Sync,
+              L: AssetLoader<A> + Parse + Dependencies + Send + 'static
+    {
+        let file = format!("{}.{}", name, ext);
+
+        if let Some(&index) = self.index.get(&file) {
+            return index;
+        }
+
+        let job = ParseJob::<A, L> {
+            name:   name.into(),
+            ext:    ext.into(),
+            _types: PhantomData,
+        };
+
+        let index = self.entries.len();
+
+        self.entries.push(Entry {
+            file: file.clone(),
+            slot: Slot::Queued,
+            deps: vec![],
+        });
+        self.index.insert(file, index);
+
+        let _ = self.jobs.send((index, Box::new(job)));
+
+        index
+    }
+
+    // Picks the asset type from the file extension, see `visit_file`
+    pub fn queue_file(&mut self, file: &str) -> Result<usize, LoaderError> {
+        visit_file(file, self)
+    }
+
+    pub fn total(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn finished(&self) -> usize {
+        self.entries.iter().filter(|e| match e.slot {
+            Slot::Uploaded | Slot::Failed => true,
+            _                             => false,
+        }).count()
+    }
+
+    // Dependencies are discovered as files are parsed, so this can go down as
+    // well as up while loading.
+    pub fn progress(&self) -> f32 {
+        if self.entries.is_empty() {
+            1.
+        } else {
+            self.finished() as f32 / self.entries.len() as f32
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.finished() == self.entries.len()
+    }
+
+    pub fn errors(&self) -> &[(String, LoaderError)] {
+        &self.errors
+    }
+
+    fn fail(&mut self, index: usize, e: LoaderError) {
+        let file = self.entries[index].file.clone();
+
+        error::report::<()>(&file, e.clone());
+        self.errors.push((file, e));
+        self.entries[index].slot = Slot::Failed;
+    }
+
+    fn receive(&mut self, index: usize, parsed: Parsed) {
+        let (pending, deps) = match parsed {
+            Ok(parsed) => parsed,
+            Err(e)     => return self.fail(index, e),
+        };
+
+        let mut dep_ids = vec![];
+
+        for dep in deps {
+            match self.queue_file(&dep) {
+                Ok(id) => dep_ids.push(id),
+                Err(e) => {
+                    let file = self.entries[index].file.clone();
+                    error::report::<()>(&file, e);
+                },
+            }
+        }
+
+        self.entries[index].deps = dep_ids;
+        self.entries[index].slot = Slot::Parsed(pending);
+
+        let cycle = {
+            let entries = &self.entries;
+
+            find_cycle(&index, |&i| &entries[i].deps[..])
+        };
+
+        // Nothing on a cycle can ever be ready, so they all fail rather than
+        // keep `is_done` from ever being true
+        if let Some(cycle) = cycle {
+            let files = cycle.iter()
+                .map(|&i| self.entries[i].file.clone())
+                .collect::<Vec<_>>();
+
+            for &i in &cycle[1..] {
+                self.fail(i, LoaderError::DependencyCycle(files.clone()));
+            }
+        }
+    }
+
+    fn next_ready(&self) -> Option<usize> {
+        self.entries.iter().position(|e| match e.slot {
+            Slot::Parsed(_) => e.deps.iter().all(
+                |&d| match self.entries[d].slot {
+                    Slot::Uploaded | Slot::Failed => true,
+                    _                             => false,
+                }
+            ),
+            _ => false,
+        })
+    }
+
+    // Uploads at most `budget` finished assets, so a frame never stalls on a
+    // long queue of textures.
+    pub fn poll(&mut self, assets: &mut AssetManager, budget: usize) {
+        while let Ok((index, parsed)) = self.done.try_recv() {
+            self.receive(index, parsed);
+        }
+
+        for _ in 0..budget {
+            let index = match self.next_ready() {
+                Some(index) => index,
+                None        => break,
+            };
+
+            let slot = ::std::mem::replace(
+                &mut self.entries[index].slot,
+                Slot::Uploaded,
+            );
+
+            if let Slot::Parsed(pending) = slot {
+                if let Err(e) = pending.upload(assets) {
+                    self.fail(index, e);
+                }
+            }
+        }
+    }
+}
+
+impl VisitFile for BackgroundLoader {
+    type Output = usize;
+
+    fn visit<A, L>(&mut self, name: &str, ext: &str) -> usize
+        where A: Any + Send + Sync,
+              L: AssetLoader<A> + Parse + Dependencies + Send + 'static
+    {
+        self.queue::<A, L>(name, ext)
+    }
+}
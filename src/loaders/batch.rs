@@ -0,0 +1,65 @@
+use std::ops::DerefMut;
+
+use amethyst::context::asset_manager::{FactoryImpl, Mesh, MeshImpl};
+use amethyst::processors::rendering::Renderable;
+use amethyst::renderer::VertexPosNormal;
+
+use super::error::LoaderError;
+use super::optimize;
+
+// One material's worth of a batched mesh. `renderable` draws `start..end` of
+// the batch's index buffer with the material's texture.
+#[derive(Clone)]
+pub struct DrawRange {
+    pub material:   String,
+    pub start:      u32,
+    pub end:        u32,
+    pub renderable: Renderable,
+}
+
+// Several sub-meshes sharing a single vertex and index buffer, grouped so that
+// each material is one contiguous range of indices.
+#[derive(Clone)]
+pub struct MeshBatch {
+    pub ranges: Vec<DrawRange>,
+}
+
+impl MeshBatch {
+    pub fn renderables(&self) -> Vec<Renderable> {
+        self.ranges.iter().map(|r| r.renderable.clone()).collect()
+    }
+}
+
+// Uploads `buf` and `slc` once and returns one `Mesh` per range, all pointing
+// into the same GPU buffers.
+pub fn new_batch_meshes<F: DerefMut<Target=FactoryImpl>>(
+    mut factory_impl: F,
+    buf: &[VertexPosNormal],
+    slc: &[u32],
+    ranges: &[(u32, u32)],
+) -> Result<Vec<Mesh>, LoaderError> {
+    match *factory_impl {
+        FactoryImpl::OpenGL { ref mut factory } => {
+            let (buffer, slice) = optimize::create_buffer(factory, buf, slc);
+
+            Ok(ranges.iter().map(|&(start, end)| {
+                let mut slice = slice.clone();
+                slice.start   = start;
+                slice.end     = end;
+
+                Mesh {
+                    mesh_impl: MeshImpl::OpenGL {
+                        buffer: buffer.clone(),
+                        slice: slice,
+                    }
+                }
+            }).collect())
+        }
+        #[cfg(windows)]
+        FactoryImpl::Direct3D {} =>
+            Err(LoaderError::UnsupportedFormat("Direct3D meshes".into())),
+        FactoryImpl::Null => Ok(
+            ranges.iter().map(|_| Mesh { mesh_impl: MeshImpl::Null }).collect()
+        ),
+    }
+}
@@ -0,0 +1,194 @@
+use image::RgbaImage;
+
+use super::error::LoaderError;
+
+const MAGIC:       &'static [u8; 4] = b"DDS ";
+const HEADER_SIZE: usize            = 128;
+// DDPF_FOURCC, set when the pixel format is named by `fourcc`
+const FOURCC:      u32              = 0x4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Block {
+    Bc1,
+    Bc2,
+    Bc3,
+}
+
+impl Block {
+    fn size(&self) -> usize {
+        match *self {
+            Block::Bc1 => 8,
+            Block::Bc2 | Block::Bc3 => 16,
+        }
+    }
+}
+
+fn le_u32(data: &[u8], at: usize) -> u32 {
+    data[at] as u32 |
+    (data[at + 1] as u32) << 8 |
+    (data[at + 2] as u32) << 16 |
+    (data[at + 3] as u32) << 24
+}
+
+fn le_u16(data: &[u8], at: usize) -> u16 {
+    data[at] as u16 | (data[at + 1] as u16) << 8
+}
+
+// Decodes the largest image of a BC1, BC2 or BC3 (DXT1, DXT3 or DXT5) .dds
+// into RGBA8 texels. Any mip levels stored after it are skipped, mipmaps come
+// from `TextureOptions` like every other format.
+pub fn decode(data: &[u8]) -> Result<RgbaImage, LoaderError> {
+    if data.len() < HEADER_SIZE || !data.starts_with(MAGIC) {
+        return Err(LoaderError::parse(None, "not a dds file"));
+    }
+
+    let height = le_u32(data, 12);
+    let width  = le_u32(data, 16);
+
+    if width == 0 || height == 0 {
+        return Err(LoaderError::parse(None, "dds image has no pixels"));
+    }
+
+    if le_u32(data, 80) & FOURCC == 0 {
+        return Err(
+            LoaderError::UnsupportedFormat("uncompressed dds".into())
+        );
+    }
+
+    let block = match &data[84..88] {
+        b"DXT1" => Block::Bc1,
+        b"DXT3" => Block::Bc2,
+        b"DXT5" => Block::Bc3,
+        other   => return Err(LoaderError::UnsupportedFormat(format!(
+            "dds format `{}`",
+            String::from_utf8_lossy(other),
+        ))),
+    };
+
+    let (cols, rows) = ((width as usize + 3) / 4, (height as usize + 3) / 4);
+    let blocks       = &data[HEADER_SIZE..];
+
+    if blocks.len() < cols * rows * block.size() {
+        return Err(LoaderError::parse(None, "truncated dds"));
+    }
+
+    let mut texels = vec![0; width as usize * height as usize * 4];
+
+    for (i, data) in blocks.chunks(block.size()).take(cols * rows).enumerate()
+    {
+        let pixels = decode_block(block, data);
+        let (bx, by) = ((i % cols) * 4, (i / cols) * 4);
+
+        // Blocks on the right and bottom edges can hang off the image
+        for (p, px) in pixels.iter().enumerate() {
+            let (x, y) = (bx + p % 4, by + p / 4);
+
+            if x < width as usize && y < height as usize {
+                let at = (y * width as usize + x) * 4;
+                texels[at..at + 4].copy_from_slice(px);
+            }
+        }
+    }
+
+    RgbaImage::from_raw(width, height, texels)
+        .ok_or(LoaderError::parse(None, "dds image too large"))
+}
+
+// Returns the 16 texels of one 4x4 block, row by row
+fn decode_block(block: Block, data: &[u8]) -> [[u8; 4]; 16] {
+    let (alpha, color) = match block {
+        Block::Bc1 => (None, data),
+        Block::Bc2 => (Some(bc2_alpha(&data[..8])), &data[8..]),
+        Block::Bc3 => (Some(bc3_alpha(&data[..8])), &data[8..]),
+    };
+
+    // Only BC1 has the three colour mode with transparent black
+    let colors  = palette(color, block == Block::Bc1);
+    let indices = le_u32(color, 4);
+
+    let mut pixels = [[0; 4]; 16];
+
+    for (p, px) in pixels.iter_mut().enumerate() {
+        *px = colors[(indices >> (2 * p) & 3) as usize];
+
+        if let Some(ref alpha) = alpha {
+            px[3] = alpha[p];
+        }
+    }
+
+    pixels
+}
+
+fn rgb565(c: u16) -> [u32; 3] {
+    let (r, g, b) = ((c >> 11) as u32, (c >> 5 & 63) as u32, (c & 31) as u32);
+
+    [r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2]
+}
+
+fn palette(data: &[u8], bc1: bool) -> [[u8; 4]; 4] {
+    let (c0, c1) = (le_u16(data, 0), le_u16(data, 2));
+    let (e0, e1) = (rgb565(c0), rgb565(c1));
+
+    let mix = |w0: u32, w1: u32| {
+        let mut px = [255; 4];
+
+        for i in 0..3 {
+            px[i] = ((e0[i] * w0 + e1[i] * w1) / (w0 + w1)) as u8;
+        }
+
+        px
+    };
+
+    if c0 > c1 || !bc1 {
+        [mix(1, 0), mix(0, 1), mix(2, 1), mix(1, 2)]
+    } else {
+        [mix(1, 0), mix(0, 1), mix(1, 1), [0; 4]]
+    }
+}
+
+// Four bits per texel
+fn bc2_alpha(data: &[u8]) -> [u8; 16] {
+    let mut alpha = [0; 16];
+
+    for (p, a) in alpha.iter_mut().enumerate() {
+        *a = (data[p / 2] >> (4 * (p % 2)) & 15) * 17;
+    }
+
+    alpha
+}
+
+// Two endpoints, then three bits per texel picking one of eight values
+// between them
+fn bc3_alpha(data: &[u8]) -> [u8; 16] {
+    let (a0, a1) = (data[0] as u32, data[1] as u32);
+
+    let mut values = [0; 8];
+    values[0] = a0 as u8;
+    values[1] = a1 as u8;
+
+    if a0 > a1 {
+        for k in 1..7 {
+            values[k + 1] = (((7 - k as u32) * a0 + k as u32 * a1) / 7) as u8;
+        }
+    } else {
+        for k in 1..5 {
+            values[k + 1] = (((5 - k as u32) * a0 + k as u32 * a1) / 5) as u8;
+        }
+
+        values[6] = 0;
+        values[7] = 255;
+    }
+
+    let bits = data[2..8].iter().rev().fold(
+        0u64,
+        |bits, &b| bits << 8 | b as u64,
+    );
+
+    let mut alpha = [0; 16];
+
+    for (p, a) in alpha.iter_mut().enumerate() {
+        *a = values[(bits >> (3 * p) & 7) as usize];
+    }
+
+    alpha
+}
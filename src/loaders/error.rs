@@ -0,0 +1,130 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+
+use tobj::LoadError;
+use xml::common::Position;
+use xml::reader::Error as XmlError;
+
+#[derive(Debug, Clone)]
+pub enum LoaderError {
+    Parse {
+        line:    Option<usize>,
+        message: String,
+    },
+    MissingDependency {
+        name: String,
+        ext:  String,
+    },
+    UnsupportedFormat(String),
+    Upload(String),
+    DependencyCycle(Vec<String>),
+}
+
+impl LoaderError {
+    pub fn parse<S: Into<String>>(line: Option<usize>, message: S) -> Self {
+        LoaderError::Parse {
+            line:    line,
+            message: message.into(),
+        }
+    }
+
+    pub fn missing(name: &str, ext: &str) -> Self {
+        LoaderError::MissingDependency {
+            name: name.into(),
+            ext:  ext.into(),
+        }
+    }
+
+    // tobj only tells us _what_ went wrong, so we walk the source ourselves to
+    // find the first line that could have produced the error.
+    pub fn from_tobj(err: LoadError, data: &[u8]) -> Self {
+        let prefix = match err {
+            LoadError::PositionParseError => Some("v"),
+            LoadError::NormalParseError   => Some("vn"),
+            LoadError::TexcoordParseError => Some("vt"),
+            LoadError::FaceParseError     => Some("f"),
+            LoadError::MaterialParseError => Some("newmtl"),
+            _                             => None,
+        };
+
+        let line = prefix.and_then(|p| first_bad_line(data, p));
+
+        LoaderError::parse(line, format!("{:?}", err))
+    }
+
+    pub fn from_xml(err: XmlError) -> Self {
+        let line = err.position().row as usize + 1;
+
+        LoaderError::parse(Some(line), err.msg())
+    }
+}
+
+fn first_bad_line(data: &[u8], prefix: &str) -> Option<usize> {
+    let text = String::from_utf8_lossy(data);
+
+    text.lines().enumerate().find(|&(_, line)| {
+        let mut words = line.split_whitespace();
+
+        if words.next() != Some(prefix) {
+            return false;
+        }
+
+        match prefix {
+            "f" => words.any(|w| w.split('/').filter(|i| !i.is_empty())
+                .any(|i| i.parse::<isize>().is_err())),
+            "newmtl" => words.next().is_none(),
+            _ => words.any(|w| w.parse::<f32>().is_err()),
+        }
+    }).map(|(i, _)| i + 1)
+}
+
+impl fmt::Display for LoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoaderError::Parse { line: Some(line), ref message } =>
+                write!(f, "parse error on line {}: {}", line, message),
+            LoaderError::Parse { line: None, ref message } =>
+                write!(f, "parse error: {}", message),
+            LoaderError::MissingDependency { ref name, ref ext } =>
+                write!(f, "missing dependency `{}.{}`", name, ext),
+            LoaderError::UnsupportedFormat(ref fmt) =>
+                write!(f, "unsupported format: {}", fmt),
+            LoaderError::Upload(ref msg) =>
+                write!(f, "GPU upload failed: {}", msg),
+            LoaderError::DependencyCycle(ref files) =>
+                write!(f, "dependency cycle: {}", files.join(" -> ")),
+        }
+    }
+}
+
+impl Error for LoaderError {
+    fn description(&self) -> &str {
+        match *self {
+            LoaderError::Parse { .. }             => "parse error",
+            LoaderError::MissingDependency { .. } => "missing dependency",
+            LoaderError::UnsupportedFormat(_)     => "unsupported format",
+            LoaderError::Upload(_)                => "GPU upload failed",
+            LoaderError::DependencyCycle(_)       => "dependency cycle",
+        }
+    }
+}
+
+// The `AssetLoader` traits can only return `Option`, so loaders stash the
+// reason for a `None` here and `loaders::load` picks it back up.
+thread_local! {
+    static LAST_ERROR: RefCell<Option<LoaderError>> = RefCell::new(None);
+}
+
+pub fn report<T>(context: &str, err: LoaderError) -> Option<T> {
+    let _ = writeln!(io::stderr(), "[loader] {}: {}", context, err);
+
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(err));
+
+    None
+}
+
+pub fn take_last_error() -> Option<LoaderError> {
+    LAST_ERROR.with(|last| last.borrow_mut().take())
+}
@@ -0,0 +1,798 @@
+use amethyst::context::asset_manager::{
+    Asset,
+    Assets,
+    AssetManager,
+    AssetLoader,
+    AssetLoaderRaw,
+    FactoryImpl,
+    Texture,
+};
+use amethyst::ecs::{Entity, World};
+use amethyst::processors::rendering::Renderable;
+use amethyst::processors::transform::{Child, LocalTransform, Transform};
+use base64;
+use yaml_rust::Yaml;
+
+use super::{split_file, Dependencies, Parse};
+use super::error::{self, LoaderError};
+use super::obj::new_mesh;
+use super::{optimize, vertex, yaml};
+use super::optimize::MeshStats;
+use super::vertex::NormalMode;
+
+#[derive(Clone, Debug)]
+pub struct GltfNode {
+    pub name:        String,
+    pub parent:      Option<usize>,
+    pub translation: [f32; 3],
+    // [w, x, y, z], the same order `LocalTransform` uses
+    pub rotation:    [f32; 4],
+    pub scale:       [f32; 3],
+    // Indices into `GltfScene::renderables`
+    pub renderables: Vec<usize>,
+}
+
+#[derive(Clone)]
+pub struct GltfScene {
+    pub nodes:       Vec<GltfNode>,
+    pub renderables: Vec<Renderable>,
+}
+
+impl GltfScene {
+    // Creates one entity per node, parented like the file's hierarchy, with an
+    // extra child entity for every primitive the node draws. Returns the node
+    // entities in file order.
+    pub fn spawn(
+        &self,
+        world: &mut World,
+        parent: Option<Entity>,
+    ) -> Vec<Entity> {
+        let mut entities: Vec<Entity> = Vec::with_capacity(self.nodes.len());
+
+        // Parents always come before their children (see `parse_nodes`)
+        for node in &self.nodes {
+            let mut l_trans = LocalTransform::default();
+            l_trans.translation = node.translation;
+            l_trans.rotation    = node.rotation;
+            l_trans.scale       = node.scale;
+
+            let node_parent = node.parent.map(|p| entities[p]).or(parent);
+
+            let mut builder = world.create_now()
+                .with(l_trans)
+                .with(Transform::default());
+
+            if let Some(p) = node_parent {
+                builder = builder.with(Child::new(p));
+            }
+
+            let entity = builder.build();
+
+            for &r in &node.renderables {
+                world.create_now()
+                    .with(LocalTransform::default())
+                    .with(Transform::default())
+                    .with(Child::new(entity))
+                    .with(self.renderables[r].clone())
+                    .build();
+            }
+
+            entities.push(entity);
+        }
+
+        entities
+    }
+}
+
+struct Primitive {
+    name:      String,
+    positions: Vec<f32>,
+    normals:   Vec<f32>,
+    texcoords: Vec<f32>,
+    indices:   Vec<u32>,
+    texture:   Option<String>,
+}
+
+// Handles `.gltf` files with embedded (`data:`) buffers and binary `.glb`
+// files. Textures are referenced by file name and loaded as dependencies.
+pub struct GltfLoader {
+    // The file's, once `parse_named` knows it
+    name:   String,
+    nodes:  Vec<GltfNode>,
+    // One entry per glTF mesh
+    meshes: Vec<Vec<Primitive>>,
+    images: Vec<String>,
+}
+
+const GLB_MAGIC: &'static [u8; 4] = b"glTF";
+const GLB_JSON:  u32              = 0x4E4F534A;
+const GLB_BIN:   u32              = 0x004E4942;
+
+// The only primitive mode that's supported
+const TRIANGLES: usize = 4;
+
+fn le_u32(data: &[u8], at: usize) -> Result<u32, LoaderError> {
+    if data.len() < at + 4 {
+        return Err(LoaderError::parse(None, "unexpected end of glb"));
+    }
+
+    Ok(
+        data[at] as u32 |
+        (data[at + 1] as u32) << 8 |
+        (data[at + 2] as u32) << 16 |
+        (data[at + 3] as u32) << 24
+    )
+}
+
+// Splits a .glb into its JSON chunk and optional binary chunk
+fn glb_chunks(data: &[u8]) -> Result<(&[u8], Option<&[u8]>), LoaderError> {
+    let mut json = None;
+    let mut bin  = None;
+    let mut at   = 12;
+
+    while at + 8 <= data.len() {
+        let len  = le_u32(data, at)? as usize;
+        let kind = le_u32(data, at + 4)?;
+
+        if data.len() < at + 8 + len {
+            return Err(LoaderError::parse(None, "truncated glb chunk"));
+        }
+
+        let chunk = &data[at + 8..at + 8 + len];
+
+        match kind {
+            GLB_JSON => json = Some(chunk),
+            GLB_BIN  => bin  = Some(chunk),
+            _        => (),
+        }
+
+        at += 8 + len;
+    }
+
+    json.map(|j| (j, bin))
+        .ok_or(LoaderError::parse(None, "glb has no JSON chunk"))
+}
+
+fn load_buffers(
+    doc: &Yaml,
+    bin: Option<&[u8]>,
+) -> Result<Vec<Vec<u8>>, LoaderError> {
+    let empty = vec![];
+    let buffers = doc["buffers"].as_vec().unwrap_or(&empty);
+
+    buffers.iter().map(|buf| match buf["uri"].as_str() {
+        None => bin.map(|b| b.to_vec()).ok_or(
+            LoaderError::parse(None, "buffer without uri outside of a glb")
+        ),
+        Some(uri) if uri.starts_with("data:") => {
+            let data = uri.splitn(2, ',').nth(1).unwrap_or("");
+            base64::decode(data).map_err(
+                |e| LoaderError::parse(None, format!("invalid base64: {}", e))
+            )
+        },
+        Some(uri) => Err(LoaderError::UnsupportedFormat(
+            format!("external glTF buffer `{}`", uri)
+        )),
+    }).collect()
+}
+
+fn index(yaml: &Yaml) -> Option<usize> {
+    yaml.as_i64().map(|i| i as usize)
+}
+
+fn read_u8(b: &[u8]) -> f64 {
+    b[0] as f64
+}
+
+fn read_u16(b: &[u8]) -> f64 {
+    (b[0] as u16 | (b[1] as u16) << 8) as f64
+}
+
+fn read_bits(b: &[u8]) -> u32 {
+    b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24
+}
+
+fn read_u32(b: &[u8]) -> f64 {
+    read_bits(b) as f64
+}
+
+fn read_f32(b: &[u8]) -> f64 {
+    f32::from_bits(read_bits(b)) as f64
+}
+
+// Reads every component of an accessor as f64, regardless of storage type
+fn read_accessor(
+    doc: &Yaml,
+    buffers: &[Vec<u8>],
+    accessor: usize,
+) -> Result<Vec<f64>, LoaderError> {
+    let acc   = &doc["accessors"][accessor];
+    let count = yaml::require(acc, "count", index)?;
+
+    let comps = match acc["type"].as_str() {
+        Some("SCALAR") => 1,
+        Some("VEC2")   => 2,
+        Some("VEC3")   => 3,
+        Some("VEC4")   => 4,
+        other          => return Err(LoaderError::UnsupportedFormat(
+            format!("accessor type {:?}", other)
+        )),
+    };
+
+    // Normalized integers map their whole range onto 0..1
+    let (size, read, max): (usize, fn(&[u8]) -> f64, f64) =
+        match yaml::require(acc, "componentType", index)? {
+            5121  => (1, read_u8, ::std::u8::MAX as f64),
+            5123  => (2, read_u16, ::std::u16::MAX as f64),
+            5125  => (4, read_u32, ::std::u32::MAX as f64),
+            5126  => (4, read_f32, 1.),
+            other => return Err(LoaderError::UnsupportedFormat(
+                format!("component type {}", other)
+            )),
+        };
+    let scale = if acc["normalized"].as_bool().unwrap_or(false) {
+        1. / max
+    } else {
+        1.
+    };
+
+    let view   = yaml::require(acc, "bufferView", index)?;
+    let view   = &doc["bufferViews"][view];
+    let buffer = buffers.get(yaml::require(view, "buffer", index)?)
+        .ok_or(LoaderError::parse(None, "buffer index out of range"))?;
+
+    let start  =
+        index(&view["byteOffset"]).unwrap_or(0) +
+        index(&acc["byteOffset"]).unwrap_or(0);
+    let stride = index(&view["byteStride"]).unwrap_or(size * comps);
+
+    if stride < size * comps {
+        return Err(LoaderError::parse(None, "accessor stride too small"));
+    }
+
+    // Checked before allocating, so a huge count can't ask for more memory
+    // than the buffer could ever fill
+    let end = match count.checked_sub(1) {
+        Some(last) => last.checked_mul(stride)
+            .and_then(|n| n.checked_add(start))
+            .and_then(|n| n.checked_add(size * comps)),
+        None       => Some(start),
+    };
+
+    if end.map_or(true, |end| end > buffer.len()) {
+        return Err(LoaderError::parse(None, "accessor out of bounds"));
+    }
+
+    let mut out = Vec::with_capacity(count * comps);
+
+    for i in 0..count {
+        for c in 0..comps {
+            let at = start + i * stride + c * size;
+
+            out.push(read(&buffer[at..at + size]) * scale);
+        }
+    }
+
+    Ok(out)
+}
+
+fn floats(
+    doc: &Yaml,
+    buffers: &[Vec<u8>],
+    accessor: &Yaml,
+) -> Result<Vec<f32>, LoaderError> {
+    match index(accessor) {
+        Some(i) => Ok(
+            read_accessor(doc, buffers, i)?.iter().map(|&f| f as f32).collect()
+        ),
+        None => Ok(vec![]),
+    }
+}
+
+fn parse_meshes(
+    doc: &Yaml,
+    buffers: &[Vec<u8>],
+) -> Result<Vec<Vec<Primitive>>, LoaderError> {
+    let empty = vec![];
+
+    doc["meshes"].as_vec().unwrap_or(&empty).iter().enumerate().map(
+        |(m, mesh)| {
+            let name = mesh["name"].as_str()
+                .map(|n| n.to_string())
+                .unwrap_or_else(|| format!("mesh{}", m));
+
+            mesh["primitives"].as_vec().unwrap_or(&empty).iter().enumerate()
+                .map(|(p, prim)| {
+                    // Strips, fans, lines and points would otherwise be read
+                    // as a triangle list
+                    match index(&prim["mode"]).unwrap_or(TRIANGLES) {
+                        TRIANGLES => (),
+                        mode      => return Err(LoaderError::UnsupportedFormat(
+                            format!("primitive mode {}", mode)
+                        )),
+                    }
+
+                    let attrs     = &prim["attributes"];
+                    let positions = floats(doc, buffers, &attrs["POSITION"])?;
+
+                    // glTF puts the texture origin top-left, OBJ bottom-left
+                    let mut texcoords =
+                        floats(doc, buffers, &attrs["TEXCOORD_0"])?;
+                    for uv in texcoords.chunks_mut(2) {
+                        if uv.len() == 2 {
+                            uv[1] = 1. - uv[1];
+                        }
+                    }
+
+                    let indices = match index(&prim["indices"]) {
+                        Some(i) => read_accessor(doc, buffers, i)?
+                            .iter()
+                            .map(|&i| i as u32)
+                            .collect(),
+                        None => (0..positions.len() as u32 / 3).collect(),
+                    };
+
+                    let count = positions.len() / 3;
+
+                    if indices.iter().any(|&i| i as usize >= count) {
+                        return Err(LoaderError::parse(
+                            None,
+                            format!("{}#{}: index out of range", name, p),
+                        ));
+                    }
+
+                    if indices.len() % 3 != 0 {
+                        return Err(LoaderError::parse(
+                            None,
+                            format!("{}#{}: partial triangle", name, p),
+                        ));
+                    }
+
+                    let texture = index(&prim["material"])
+                        .and_then(|mat| index(
+                            &doc["materials"][mat]["pbrMetallicRoughness"]
+                                ["baseColorTexture"]["index"]
+                        ))
+                        .and_then(
+                            |tex| index(&doc["textures"][tex]["source"])
+                        )
+                        .and_then(|img| doc["images"][img]["uri"].as_str())
+                        // Embedded images aren't supported
+                        .and_then(|uri| if uri.starts_with("data:") {
+                            None
+                        } else {
+                            Some(uri)
+                        })
+                        .map(|uri| uri.to_string());
+
+                    Ok(Primitive {
+                        name:      format!("{}#{}", name, p),
+                        normals:   floats(doc, buffers, &attrs["NORMAL"])?,
+                        positions: positions,
+                        texcoords: texcoords,
+                        indices:   indices,
+                        texture:   texture,
+                    })
+                })
+                .collect()
+        }
+    ).collect()
+}
+
+// Orders nodes so parents always come first, and records each one's parent
+fn parse_nodes(doc: &Yaml) -> Result<Vec<GltfNode>, LoaderError> {
+    let empty = vec![];
+    let nodes = doc["nodes"].as_vec().unwrap_or(&empty);
+
+    let mut parents = vec![None; nodes.len()];
+
+    for (i, node) in nodes.iter().enumerate() {
+        for child in node["children"].as_vec().unwrap_or(&empty) {
+            let child = index(child).ok_or(
+                LoaderError::parse(None, "invalid child index")
+            )?;
+
+            if child >= nodes.len() || parents[child].is_some() {
+                return Err(LoaderError::parse(None, "invalid node hierarchy"));
+            }
+
+            parents[child] = Some(i);
+        }
+    }
+
+    let mut order = (0..nodes.len())
+        .filter(|&i| parents[i].is_none())
+        .collect::<Vec<_>>();
+    let mut next = 0;
+
+    while next < order.len() {
+        let i = order[next];
+        order.extend(
+            node_children(&nodes[i]).into_iter().filter(|&c| c < nodes.len())
+        );
+        next += 1;
+    }
+
+    if order.len() != nodes.len() {
+        return Err(LoaderError::parse(None, "node hierarchy has a cycle"));
+    }
+
+    let mut position = vec![0; nodes.len()];
+    for (new, &old) in order.iter().enumerate() {
+        position[old] = new;
+    }
+
+    order.iter().map(|&i| {
+        let node = &nodes[i];
+
+        let (translation, rotation, scale) = match node["matrix"] {
+            Yaml::BadValue => (
+                yaml::triple(&node["translation"]).unwrap_or([0.; 3]),
+                yaml::numbers(&node["rotation"])
+                    .and_then(|r| if r.len() == 4 {
+                        Some([r[3], r[0], r[1], r[2]])
+                    } else {
+                        None
+                    })
+                    .unwrap_or([1., 0., 0., 0.]),
+                yaml::triple(&node["scale"]).unwrap_or([1.; 3]),
+            ),
+            ref matrix => match yaml::numbers(matrix) {
+                Some(ref m) if m.len() == 16 => decompose(m)?,
+                _ => return Err(LoaderError::parse(None, "invalid matrix")),
+            },
+        };
+
+        Ok(GltfNode {
+            name:        node["name"].as_str()
+                .map(|n| n.to_string())
+                .unwrap_or_else(|| format!("node{}", i)),
+            parent:      parents[i].map(|p| position[p]),
+            translation: translation,
+            rotation:    rotation,
+            scale:       scale,
+            // Mesh indices for now, resolved to renderables once uploaded
+            renderables: index(&node["mesh"]).into_iter().collect(),
+        })
+    }).collect()
+}
+
+// Splits a column-major node matrix into translation, rotation as [w, x, y, z]
+// and scale. Transforms don't have shear or perspective, so matrices with
+// either are rejected rather than losing part of them.
+fn decompose(
+    m: &[f32],
+) -> Result<([f32; 3], [f32; 4], [f32; 3]), LoaderError> {
+    const EPSILON: f32 = 1e-4;
+
+    let unsupported = || Err(LoaderError::UnsupportedFormat(
+        "node matrix with shear or perspective".into()
+    ));
+
+    if m[3].abs() > EPSILON ||
+        m[7].abs() > EPSILON ||
+        m[11].abs() > EPSILON ||
+        (m[15] - 1.).abs() > EPSILON
+    {
+        return unsupported();
+    }
+
+    let length = |c: usize| {
+        (m[c * 4] * m[c * 4] + m[c * 4 + 1] * m[c * 4 + 1] +
+            m[c * 4 + 2] * m[c * 4 + 2]).sqrt()
+    };
+    let mut scale = [length(0), length(1), length(2)];
+
+    if scale.iter().any(|&s| s < EPSILON) {
+        return unsupported();
+    }
+
+    // A mirrored basis has a negative determinant, put the flip in x
+    let det =
+        m[0] * (m[5] * m[10] - m[9] * m[6]) -
+        m[4] * (m[1] * m[10] - m[9] * m[2]) +
+        m[8] * (m[1] * m[6] - m[5] * m[2]);
+
+    if det < 0. {
+        scale[0] = -scale[0];
+    }
+
+    // r[row][col]
+    let mut r = [[0.; 3]; 3];
+    for col in 0..3 {
+        for row in 0..3 {
+            r[row][col] = m[col * 4 + row] / scale[col];
+        }
+    }
+
+    for &(a, b) in &[(0, 1), (0, 2), (1, 2)] {
+        let dot = r[0][a] * r[0][b] + r[1][a] * r[1][b] + r[2][a] * r[2][b];
+
+        if dot.abs() > EPSILON {
+            return unsupported();
+        }
+    }
+
+    let trace    = r[0][0] + r[1][1] + r[2][2];
+    let rotation = if trace > 0. {
+        let s = (trace + 1.).sqrt() * 2.;
+        [
+            s / 4.,
+            (r[2][1] - r[1][2]) / s,
+            (r[0][2] - r[2][0]) / s,
+            (r[1][0] - r[0][1]) / s,
+        ]
+    } else if r[0][0] > r[1][1] && r[0][0] > r[2][2] {
+        let s = (1. + r[0][0] - r[1][1] - r[2][2]).sqrt() * 2.;
+        [
+            (r[2][1] - r[1][2]) / s,
+            s / 4.,
+            (r[0][1] + r[1][0]) / s,
+            (r[0][2] + r[2][0]) / s,
+        ]
+    } else if r[1][1] > r[2][2] {
+        let s = (1. + r[1][1] - r[0][0] - r[2][2]).sqrt() * 2.;
+        [
+            (r[0][2] - r[2][0]) / s,
+            (r[0][1] + r[1][0]) / s,
+            s / 4.,
+            (r[1][2] + r[2][1]) / s,
+        ]
+    } else {
+        let s = (1. + r[2][2] - r[0][0] - r[1][1]).sqrt() * 2.;
+        [
+            (r[1][0] - r[0][1]) / s,
+            (r[0][2] + r[2][0]) / s,
+            (r[1][2] + r[2][1]) / s,
+            s / 4.,
+        ]
+    };
+
+    Ok(([m[12], m[13], m[14]], rotation, scale))
+}
+
+fn node_children(node: &Yaml) -> Vec<usize> {
+    node["children"].as_vec()
+        .map(|c| c.iter().filter_map(index).collect())
+        .unwrap_or(vec![])
+}
+
+impl Parse for GltfLoader {
+    fn parse(data: &[u8]) -> Result<Self, LoaderError> {
+        let (json, bin) = if data.starts_with(GLB_MAGIC) {
+            glb_chunks(data)?
+        } else {
+            (data, None)
+        };
+
+        let doc = yaml::parse(json)?;
+
+        let version = doc["asset"]["version"].as_str().unwrap_or("");
+
+        if !version.starts_with("2.") {
+            return Err(LoaderError::UnsupportedFormat(
+                "only glTF 2.0 is supported".into()
+            ));
+        }
+
+        let buffers = load_buffers(&doc, bin)?;
+        let meshes  = parse_meshes(&doc, &buffers)?;
+
+        let mut images = vec![];
+        for prim in meshes.iter().flat_map(|m| m) {
+            if let Some(ref tex) = prim.texture {
+                if !images.contains(tex) {
+                    images.push(tex.clone());
+                }
+            }
+        }
+
+        Ok(GltfLoader {
+            name:   "gltf".into(),
+            nodes:  parse_nodes(&doc)?,
+            meshes: meshes,
+            images: images,
+        })
+    }
+
+    // Mesh names are only unique within a file, so they're uploaded as
+    // `file#mesh#primitive`
+    fn parse_named(name: &str, data: &[u8]) -> Result<Self, LoaderError> {
+        let mut loader = Self::parse(data)?;
+        loader.name = name.into();
+
+        for prim in loader.meshes.iter_mut().flat_map(|m| m) {
+            prim.name = format!("{}#{}", name, prim.name);
+        }
+
+        Ok(loader)
+    }
+}
+
+impl Dependencies for GltfLoader {
+    fn dependencies(&self) -> Vec<String> {
+        self.images.clone()
+    }
+}
+
+impl AssetLoaderRaw for GltfLoader {
+    fn from_raw(_assets: &Assets, data: &[u8]) -> Option<Self> {
+        match Self::parse(data) {
+            Ok(loader) => Some(loader),
+            Err(e)     => error::report("gltf", e),
+        }
+    }
+}
+
+fn texture_name(assets: &AssetManager, file: &Option<String>) -> String {
+    let name = file.as_ref().map(|f| split_file(f, "png").0);
+
+    match name {
+        Some(name) if assets.id_from_name(name)
+            .map(|id| assets.read_assets::<Texture>().get(id).is_some())
+            .unwrap_or(false) => name.to_string(),
+        Some(name) => {
+            error::report::<()>("gltf", LoaderError::missing(name, "png"));
+            "default".into()
+        },
+        None => "default".into(),
+    }
+}
+
+fn load_scene(
+    assets: &mut AssetManager,
+    data: GltfLoader,
+) -> Option<GltfScene> {
+    let mut renderables = vec![];
+    // Per glTF mesh, the renderables its primitives turned into
+    let mut mesh_renderables = vec![];
+    let mode = assets.get_loader_mut::<NormalMode>()
+        .map(|m| *m)
+        .unwrap_or_default();
+    let mut stats = MeshStats::default();
+
+    for mesh in data.meshes {
+        let mut ids = vec![];
+
+        for prim in mesh {
+            let (verts, indices) = vertex::assemble(
+                &prim.positions,
+                &prim.normals,
+                &prim.texcoords,
+                &prim.indices,
+                mode,
+            );
+            let (verts, indices, mesh_stats) =
+                optimize::optimize(verts, indices);
+            stats.add(&mesh_stats);
+
+            let mesh = assets.get_loader_mut::<FactoryImpl>()
+                .ok_or(LoaderError::Upload("no factory".into()))
+                .and_then(|f| new_mesh(f, &verts, &indices));
+
+            match mesh {
+                Ok(mesh) => { assets.add_asset(&prim.name, mesh); },
+                Err(e)   => {
+                    error::report::<()>(&prim.name, e);
+                    continue;
+                },
+            }
+
+            let tex = texture_name(assets, &prim.texture);
+
+            ids.push(renderables.len());
+            renderables.push(Renderable::new(&prim.name, &tex, &tex));
+        }
+
+        mesh_renderables.push(ids);
+    }
+
+    stats.log(&data.name);
+
+    let nodes = data.nodes.into_iter().map(|mut node| {
+        node.renderables = node.renderables.iter()
+            .filter_map(|&m| mesh_renderables.get(m))
+            .flat_map(|ids| ids.iter().cloned())
+            .collect();
+        node
+    }).collect();
+
+    Some(GltfScene {
+        nodes:       nodes,
+        renderables: renderables,
+    })
+}
+
+impl AssetLoader<GltfScene> for GltfLoader {
+    fn from_data(_assets: &mut Assets, _data: Self) -> Option<GltfScene> {
+        error::report(
+            "gltf",
+            LoaderError::UnsupportedFormat(
+                "glTF scenes must be loaded through the asset manager".into()
+            ),
+        )
+    }
+
+    fn load_from_data(
+        assets: &mut AssetManager,
+        data: Self,
+    ) -> Option<GltfScene> {
+        load_scene(assets, data)
+    }
+}
+
+impl AssetLoader<Vec<Renderable>> for GltfLoader {
+    fn from_data(
+        _assets: &mut Assets,
+        _data: Self,
+    ) -> Option<Vec<Renderable>> {
+        error::report(
+            "gltf",
+            LoaderError::UnsupportedFormat(
+                "glTF renderables must be loaded through the asset manager"
+                    .into()
+            ),
+        )
+    }
+
+    fn load_from_data(
+        assets: &mut AssetManager,
+        data: Self,
+    ) -> Option<Vec<Renderable>> {
+        load_scene(assets, data).map(|scene| scene.renderables)
+    }
+}
+
+// Spawns an already loaded glTF scene, see `GltfScene::spawn`
+pub fn spawn_scene(
+    assets: &AssetManager,
+    world: &mut World,
+    name: &str,
+    parent: Option<Entity>,
+) -> Result<Vec<Entity>, LoaderError> {
+    let scene = {
+        let store = assets.read_assets();
+        let scene: Option<&Asset<GltfScene>> = assets.id_from_name(name)
+            .and_then(|id| store.get(id));
+
+        scene.map(|s| s.0.clone())
+            .ok_or_else(|| LoaderError::missing(name, "gltf"))?
+    };
+
+    Ok(scene.spawn(world, parent))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decompose;
+
+    fn close(a: &[f32], b: &[f32]) -> bool {
+        a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-5)
+    }
+
+    #[test]
+    fn decompose_trs() {
+        // A quarter turn about z, scaled by (2, 3, 4) and moved by (1, 2, 3)
+        let (t, r, s) = decompose(&[
+            0.,  2., 0., 0.,
+            -3., 0., 0., 0.,
+            0.,  0., 4., 0.,
+            1.,  2., 3., 1.,
+        ]).unwrap();
+        let half = 0.5f32.sqrt();
+
+        assert!(close(&t, &[1., 2., 3.]), "{:?}", t);
+        assert!(close(&r, &[half, 0., 0., half]), "{:?}", r);
+        assert!(close(&s, &[2., 3., 4.]), "{:?}", s);
+    }
+
+    #[test]
+    fn decompose_shear() {
+        assert!(decompose(&[
+            1., 0., 0., 0.,
+            1., 1., 0., 0.,
+            0., 0., 1., 0.,
+            0., 0., 0., 1.,
+        ]).is_err());
+    }
+}
@@ -1,6 +1,408 @@
+pub mod background;
+pub mod batch;
+pub mod dds;
+pub mod error;
+pub mod gltf;
 pub mod texture;
 pub mod material;
 pub mod obj;
+pub mod optimize;
+pub mod prefab;
+pub mod vertex;
+pub mod sprite;
+pub mod tiled;
+pub mod xml;
+pub mod yaml;
 
-pub use self::texture::PngTextureLoader;
-pub use self::obj::ObjLoader;
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use amethyst::context::asset_manager::{
+    Asset,
+    AssetId,
+    AssetLoader,
+    AssetManager,
+    AssetStore,
+    Texture,
+};
+use amethyst::processors::rendering::Renderable;
+
+use stores::AssetPaths;
+use self::obj::{MtlLib, MtlLoader};
+use self::texture::{Png, Jpeg, Tga, Bmp, Gif, Dds};
+
+pub use self::background::BackgroundLoader;
+pub use self::batch::{DrawRange, MeshBatch};
+pub use self::error::LoaderError;
+pub use self::gltf::{GltfLoader, GltfNode, GltfScene};
+pub use self::texture::{
+    ImageTextureLoader,
+    PngTextureLoader,
+    DecodedImage,
+    TextureOptions,
+};
+pub use self::obj::{ObjLoader, ObjObject, ObjObjects};
+pub use self::prefab::{Prefab, PrefabLoader};
+pub use self::sprite::{
+    Clip,
+    PlayMode,
+    SpriteSheet,
+    SpriteSheetLoader,
+    UvRect,
+};
+pub use self::tiled::{
+    MapObject,
+    Property,
+    Properties,
+    Shape,
+    TiledLoader,
+    TiledMap,
+};
+
+// Turns raw file contents into a loader without touching the asset manager, so
+// it can be done off the main thread.
+pub trait Parse: Sized {
+    fn parse(data: &[u8]) -> Result<Self, LoaderError>;
+
+    // For loaders that name what they upload after their file. The asset
+    // manager never says which file it's reading, so only `parse_file` and hot
+    // reloading get here.
+    fn parse_named(_name: &str, data: &[u8]) -> Result<Self, LoaderError> {
+        Self::parse(data)
+    }
+}
+
+// Files (relative to the asset root, extension included) that have to be
+// loaded before this one can be handed to the asset manager. Loaders only look
+// dependencies up by name once they're uploaded, they never load them; `load`
+// and the `BackgroundLoader` do that first.
+pub trait Dependencies {
+    fn dependencies(&self) -> Vec<String> {
+        vec![]
+    }
+}
+
+// Something to do with a file once its extension has picked the asset and
+// loader types, see `visit_file`.
+pub trait VisitFile {
+    type Output;
+
+    fn visit<A, L>(&mut self, name: &str, ext: &str) -> Self::Output
+        where A: Any + Send + Sync,
+              L: AssetLoader<A> + Parse + Dependencies + Send + 'static;
+}
+
+// Picks the asset type from the file extension, the same way the loaders are
+// registered in `main`.
+pub fn visit_file<V: VisitFile>(
+    file: &str,
+    visitor: &mut V,
+) -> Result<V::Output, LoaderError> {
+    let (name, ext) = split_file(file, "");
+
+    let output = match ext {
+        "png" =>
+            visitor.visit::<Texture, ImageTextureLoader<Png>>(name, ext),
+        "jpg" | "jpeg" =>
+            visitor.visit::<Texture, ImageTextureLoader<Jpeg>>(name, ext),
+        "tga" =>
+            visitor.visit::<Texture, ImageTextureLoader<Tga>>(name, ext),
+        "bmp" =>
+            visitor.visit::<Texture, ImageTextureLoader<Bmp>>(name, ext),
+        "gif" =>
+            visitor.visit::<Texture, ImageTextureLoader<Gif>>(name, ext),
+        "dds" =>
+            visitor.visit::<Texture, ImageTextureLoader<Dds>>(name, ext),
+        "mtl" =>
+            visitor.visit::<MtlLib, MtlLoader>(name, ext),
+        "obj" =>
+            visitor.visit::<ObjObjects, ObjLoader>(name, ext),
+        "gltf" | "glb" =>
+            visitor.visit::<GltfScene, GltfLoader>(name, ext),
+        "prefab" =>
+            visitor.visit::<Prefab, PrefabLoader>(name, ext),
+        "sheet" =>
+            visitor.visit::<SpriteSheet, SpriteSheetLoader>(name, ext),
+        "tmx" | "tmj" =>
+            visitor.visit::<TiledMap, TiledLoader>(name, ext),
+        _ => return Err(LoaderError::UnsupportedFormat(file.into())),
+    };
+
+    Ok(output)
+}
+
+// Reads `name.ext` out of `store` and parses it, without uploading anything
+pub fn parse_file<L: Parse>(
+    store: &AssetStore,
+    name: &str,
+    ext: &str,
+) -> Result<L, LoaderError> {
+    let mut buf = vec![];
+
+    if store.load_asset(name, ext, &mut buf).is_none() {
+        return Err(LoaderError::missing(name, ext));
+    }
+
+    L::parse_named(name, &buf)
+}
+
+// Splits `quad_mtl.mtl` into `("quad_mtl", "mtl")`, falling back to
+// `default_ext` for bare names.
+pub fn split_file<'a>(
+    file: &'a str,
+    default_ext: &'a str,
+) -> (&'a str, &'a str) {
+    let mut split = file.rsplitn(2, '.');
+
+    match (split.next(), split.next()) {
+        (Some(ext), Some(name)) => (name, ext),
+        _                       => (file, default_ext),
+    }
+}
+
+fn loaded<A: Any + Send + Sync>(
+    assets: &AssetManager,
+    name: &str,
+) -> Option<AssetId> {
+    assets.id_from_name(name)
+        .and_then(|id| assets.read_assets::<A>().get(id).map(|_| id))
+}
+
+// Follows dependency edges out of `node` looking for a way back to it, going
+// through every node at most once. Returns the cycle as `node`, the nodes on
+// the way and `node` again. Shared by `load` and the `BackgroundLoader`.
+fn find_cycle<'a, N, F>(node: &N, deps: F) -> Option<Vec<N>>
+    where N: Clone + Eq + Hash + 'a,
+          F: Fn(&N) -> &'a [N]
+{
+    let mut visited = HashSet::new();
+    // Each node on the current path with the next edge to follow out of it
+    let mut path    = vec![(node.clone(), 0)];
+
+    visited.insert(node.clone());
+
+    loop {
+        let dep = match path.last_mut() {
+            Some(&mut (ref current, ref mut next)) => {
+                *next += 1;
+                deps(current).get(*next - 1).cloned()
+            },
+            None => return None,
+        };
+
+        match dep {
+            Some(ref dep) if dep == node => {
+                let mut cycle = path.into_iter()
+                    .map(|(n, _)| n)
+                    .collect::<Vec<_>>();
+                cycle.push(dep.clone());
+
+                return Some(cycle);
+            },
+            Some(dep) => if visited.insert(dep.clone()) {
+                path.push((dep, 0));
+            },
+            None => { path.pop(); },
+        }
+    }
+}
+
+// Loads files depth first, each after everything it depends on. `edges` holds
+// every file's dependencies once it has been parsed, so a file whose
+// dependencies lead back to it is a cycle and everything on it fails.
+struct DependencyWalk<'a> {
+    assets: &'a mut AssetManager,
+    store:  &'a AssetStore,
+    edges:  HashMap<String, Vec<String>>,
+    cycles: HashMap<String, Vec<String>>,
+}
+
+impl<'a> DependencyWalk<'a> {
+    fn dependencies(
+        &mut self,
+        file: String,
+        deps: Vec<String>,
+    ) -> Result<(), LoaderError> {
+        self.edges.insert(file.clone(), deps.clone());
+
+        let cycle = {
+            let edges = &self.edges;
+
+            find_cycle(&file, |f| edges.get(f).map(|d| &d[..]).unwrap_or(&[]))
+        };
+
+        if let Some(cycle) = cycle {
+            for f in &cycle {
+                self.cycles.insert(f.clone(), cycle.clone());
+            }
+        }
+
+        self.each_dependency(&file, deps)
+    }
+
+    fn cycle(&self, file: &str) -> Result<(), LoaderError> {
+        match self.cycles.get(file) {
+            Some(cycle) => Err(LoaderError::DependencyCycle(cycle.clone())),
+            None        => Ok(()),
+        }
+    }
+
+    fn each_dependency(
+        &mut self,
+        file: &str,
+        deps: Vec<String>,
+    ) -> Result<(), LoaderError> {
+        for dep in deps {
+            self.cycle(file)?;
+
+            // Like the `BackgroundLoader`, a dependency that can't be loaded
+            // is only reported, and the file that needed it decides whether
+            // it can do without. Files on a cycle can't load at all.
+            if let Err(e) = visit_file(&dep, self).and_then(|r| r) {
+                error::report::<()>(&dep, e);
+            }
+        }
+
+        self.cycle(file)
+    }
+}
+
+impl<'a> VisitFile for DependencyWalk<'a> {
+    type Output = Result<(), LoaderError>;
+
+    fn visit<A, L>(&mut self, name: &str, ext: &str) -> Self::Output
+        where A: Any + Send + Sync,
+              L: AssetLoader<A> + Parse + Dependencies + Send + 'static
+    {
+        if loaded::<A>(self.assets, name).is_some() {
+            return Ok(());
+        }
+
+        let file = format!("{}.{}", name, ext);
+        self.cycle(&file)?;
+
+        let loader = parse_file::<L>(self.store, name, ext)?;
+        let deps   = loader.dependencies();
+
+        self.dependencies(file, deps)?;
+
+        error::take_last_error();
+
+        self.assets.load_asset_from_data::<A, L>(name, loader)
+            .map(|_| ())
+            .ok_or_else(
+                || error::take_last_error()
+                    .unwrap_or_else(|| LoaderError::Upload(name.into()))
+            )
+    }
+}
+
+// The file `load` was asked for. When it's wanted as the asset its extension
+// usually loads as, it goes through the walk like a dependency. Otherwise only
+// its dependencies do, and the asset manager reads it as whatever was asked.
+// Returns whether the file was uploaded.
+struct Requested<'a, 'b: 'a> {
+    walk:  &'a mut DependencyWalk<'b>,
+    asset: TypeId,
+}
+
+impl<'a, 'b: 'a> VisitFile for Requested<'a, 'b> {
+    type Output = Result<bool, LoaderError>;
+
+    fn visit<A, L>(&mut self, name: &str, ext: &str) -> Self::Output
+        where A: Any + Send + Sync,
+              L: AssetLoader<A> + Parse + Dependencies + Send + 'static
+    {
+        if TypeId::of::<A>() == self.asset {
+            return self.walk.visit::<A, L>(name, ext).map(|_| true);
+        }
+
+        let deps = parse_file::<L>(self.walk.store, name, ext)?.dependencies();
+
+        self.walk.dependencies(format!("{}.{}", name, ext), deps).map(|_| false)
+    }
+}
+
+// Loads `name.ext` as an `A`, after loading everything it depends on. Assets
+// that are already loaded, like everything a `BackgroundLoader` preloaded, are
+// returned as they are. Dependencies are read through the `AssetPaths` added
+// to the asset manager with `add_loader`, and aren't loaded without it.
+pub fn load<A: Any + Send + Sync>(
+    assets: &mut AssetManager,
+    name: &str,
+    ext: &str,
+) -> Result<AssetId, LoaderError> {
+    if let Some(id) = loaded::<A>(assets, name) {
+        return Ok(id);
+    }
+
+    let store    = assets.get_loader_mut::<AssetPaths>().map(|p| p.store());
+    let uploaded = match store {
+        Some(ref store) => {
+            let mut walk = DependencyWalk {
+                assets: assets,
+                store:  store,
+                edges:  HashMap::new(),
+                cycles: HashMap::new(),
+            };
+            let mut requested = Requested {
+                walk:  &mut walk,
+                asset: TypeId::of::<A>(),
+            };
+
+            match visit_file(&format!("{}.{}", name, ext), &mut requested) {
+                Ok(uploaded)                           => uploaded?,
+                // Nothing that can declare dependencies
+                Err(LoaderError::UnsupportedFormat(_)) => false,
+                Err(e)                                 => return Err(e),
+            }
+        },
+        None => false,
+    };
+
+    if uploaded {
+        return loaded::<A>(assets, name)
+            .ok_or_else(|| LoaderError::Upload(name.into()));
+    }
+
+    error::take_last_error();
+
+    assets.load_asset::<A>(name, ext).ok_or_else(
+        || error::take_last_error()
+            .unwrap_or_else(|| LoaderError::missing(name, ext))
+    )
+}
+
+// Loads one object out of a model file addressed as `file#object`, e.g.
+// `quad#Quad_face`. Without an `#object` part the file's first object is used.
+pub fn load_object(
+    assets: &mut AssetManager,
+    path: &str,
+    ext: &str,
+) -> Result<Renderable, LoaderError> {
+    let mut split = path.splitn(2, '#');
+    let file      = split.next().unwrap_or(path);
+
+    match split.next() {
+        Some(object) => {
+            let id      = load::<ObjObjects>(assets, file, ext)?;
+            let objects = assets.read_assets();
+            let objects: Option<&Asset<ObjObjects>> = objects.get(id);
+
+            objects.and_then(|o| o.0.get(object))
+                .map(|o| o.renderable.clone())
+                .ok_or_else(|| LoaderError::missing(path, ext))
+        },
+        None => {
+            let id          = load::<Vec<Renderable>>(assets, file, ext)?;
+            let renderables = assets.read_assets();
+            let renderables: Option<&Asset<Vec<Renderable>>> =
+                renderables.get(id);
+
+            renderables.and_then(|r| r.0.first())
+                .cloned()
+                .ok_or_else(|| LoaderError::missing(path, ext))
+        },
+    }
+}
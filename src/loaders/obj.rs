@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use tobj::{parse_obj, parse_mtl, Model, Material};
 use amethyst::context::asset_manager::{
     Asset,
+    AssetId,
     Assets,
     AssetManager,
     AssetLoader,
@@ -13,13 +14,50 @@ use amethyst::context::asset_manager::{
 use amethyst::processors::rendering::Renderable;
 use amethyst::renderer::VertexPosNormal;
 
+use super::{split_file, Dependencies, Parse};
+use super::error::{self, LoaderError};
+use super::batch::{new_batch_meshes, DrawRange, MeshBatch};
+use super::optimize::{self, MeshStats};
+use super::vertex::{self, NormalMode};
+
 pub type MtlLib = HashMap<String, Material>;
 
 pub struct MtlLoader(MtlLib);
 
+impl Parse for MtlLoader {
+    fn parse(data: &[u8]) -> Result<Self, LoaderError> {
+        parse_mtl(&mut &data[..])
+            .map(MtlLoader)
+            .map_err(|e| LoaderError::from_tobj(e, data))
+    }
+}
+
+impl Dependencies for MtlLoader {
+    fn dependencies(&self) -> Vec<String> {
+        let mut out = vec![];
+
+        for mat in self.0.values() {
+            for tex in &[
+                &mat.ambient_texture,
+                &mat.diffuse_texture,
+                &mat.specular_texture,
+            ] {
+                if !tex.is_empty() && !out.contains(*tex) {
+                    out.push(tex.to_string());
+                }
+            }
+        }
+
+        out
+    }
+}
+
 impl AssetLoaderRaw for MtlLoader {
-    fn from_raw(_assets: &Assets, mut data: &[u8]) -> Option<Self> {
-        parse_mtl(&mut data).map(MtlLoader).ok()
+    fn from_raw(_assets: &Assets, data: &[u8]) -> Option<Self> {
+        match Self::parse(data) {
+            Ok(loader) => Some(loader),
+            Err(e)     => error::report("mtl", e),
+        }
     }
 }
 
@@ -36,107 +74,198 @@ impl AssetLoader<MtlLib> for MtlLoader {
 // it into OpenGL raw unless you want to have a Really Bad Time.
 pub struct ObjLoader((Vec<Model>, Vec<String>));
 
+impl Parse for ObjLoader {
+    fn parse(data: &[u8]) -> Result<Self, LoaderError> {
+        parse_obj(&mut &data[..])
+            .map(ObjLoader)
+            .map_err(|e| LoaderError::from_tobj(e, data))
+    }
+}
+
+impl Dependencies for ObjLoader {
+    fn dependencies(&self) -> Vec<String> {
+        (self.0).1.iter()
+            .map(|lib| {
+                let (name, ext) = split_file(lib, "mtl");
+                format!("{}.{}", name, ext)
+            })
+            .collect()
+    }
+}
+
 impl AssetLoaderRaw for ObjLoader {
-    fn from_raw(_assets: &Assets, mut data: &[u8]) -> Option<Self> {
-        parse_obj(&mut data).map(ObjLoader).ok()
+    fn from_raw(_assets: &Assets, data: &[u8]) -> Option<Self> {
+        match Self::parse(data) {
+            Ok(loader) => Some(loader),
+            Err(e)     => error::report("obj", e),
+        }
     }
 }
 
+#[derive(Clone)]
+pub struct ObjObject {
+    pub renderable: Renderable,
+    pub mesh:       AssetId,
+}
+
+pub type ObjObjects = HashMap<String, ObjObject>;
+
 impl AssetLoader<Vec<Renderable>> for ObjLoader {
+    // Meshes are registered under their object names, which needs the asset
+    // manager rather than just `Assets`.
     fn from_data(
         _assets: &mut Assets,
         _data: Self,
     ) -> Option<Vec<Renderable>> {
-        // TODO: Use mtl and textures here, and only preload them in
-        //       load_from_data
-        // TODO: Should load_from_data be replaced with a function that returns
-        //       a list of assets to preload?
-        None
+        error::report("obj", needs_manager("renderables"))
     }
 
     fn load_from_data(
         assets: &mut AssetManager,
-        mut data: Self,
+        data: Self,
     ) -> Option<Vec<Renderable>> {
-        let mut lib_ids = (data.0).1.drain(..).filter_map(
-            |name| {
-                let mut split = name.rsplitn(2, '.');
+        load_objects(assets, data).map(
+            |objs| objs.into_iter().map(|(_, obj)| obj.renderable).collect()
+        )
+    }
+}
+
+impl AssetLoader<ObjObjects> for ObjLoader {
+    fn from_data(_assets: &mut Assets, _data: Self) -> Option<ObjObjects> {
+        error::report("obj", needs_manager("objects"))
+    }
+
+    fn load_from_data(
+        assets: &mut AssetManager,
+        data: Self,
+    ) -> Option<ObjObjects> {
+        load_objects(assets, data).map(|objs| objs.into_iter().collect())
+    }
+}
+
+fn needs_manager(what: &str) -> LoaderError {
+    LoaderError::UnsupportedFormat(
+        format!("obj {} must be loaded through the asset manager", what)
+    )
+}
 
-                let o_ext  = split.next();
-                let o_name = split.next();
+// Material libraries must already be loaded (see `Dependencies`); missing ones
+// are reported and their objects fall back to the default texture.
+fn collect_materials<I: Iterator<Item=String>>(
+    assets: &AssetManager,
+    libs: I,
+) -> MtlLib {
+    let assets_store = assets.read_assets();
 
-                let (name, ext) = match (o_name, o_ext) {
-                    (None, Some(st))     => (st, "mtl"),
-                    (Some(nm), Some(ex)) => (nm, ex),
-                    _                    => return None,
-                };
+    libs.fold(
+        HashMap::new(),
+        |mut last, lib| {
+            let (name, ext) = split_file(&lib, "mtl");
+            let asset: Option<&Asset<MtlLib>> = assets.id_from_name(name)
+                .and_then(|id| assets_store.get(id));
 
-                assets.load_asset::<MtlLib>(name, ext)
+            match asset {
+                Some(asset) => last.extend(
+                    asset.0.iter().map(|(a, b)| (a.clone(), b.clone()))
+                ),
+                None => {
+                    error::report::<()>(
+                        "obj",
+                        LoaderError::missing(name, ext),
+                    );
+                },
             }
-        ).collect::<Vec<_>>();
-
-        let materials: MtlLib = {
-            let assets_store = assets.read_assets();
-
-            lib_ids.drain(..).fold(
-                HashMap::new(),
-                |mut last, cur| {
-                    if let Some(asset) =
-                        assets_store.get(cur)
-                    {
-                        let asset: &Asset<MtlLib> = asset;
-                        last.extend(
-                            asset.0.iter().map(|(a, b)| (a.clone(), b.clone()))
-                        );
-                    }
-
-                    last
-                }
-            )
-        };
 
-        (data.0).0.drain(..).map(|model| {
-            let mesh = {
-                let factory_impl = assets.get_loader_mut::<FactoryImpl>()
-                    .expect("Unable to retrieve factory");
+            last
+        }
+    )
+}
+
+// The asset manager never says which file it's loading, so objs are named
+// after the objects in them
+fn asset_name(models: &[Model]) -> String {
+    models.iter()
+        .map(|m| &m.name[..])
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+fn texture_for<'a>(model: &Model, materials: &'a MtlLib) -> &'a str {
+    model.mesh.material.as_ref()
+        .and_then(|m| materials.get(m))
+        .map(|m| &m.diffuse_texture)
+        .and_then(|tex| tex.rsplitn(2, '.').skip(1).next())
+        .unwrap_or("default")
+}
+
+fn load_objects(
+    assets: &mut AssetManager,
+    mut data: ObjLoader,
+) -> Option<Vec<(String, ObjObject)>> {
+    let materials = collect_materials(assets, (data.0).1.drain(..));
+    let mode      = assets.get_loader_mut::<NormalMode>()
+        .map(|m| *m)
+        .unwrap_or_default();
 
-                let verts = verts_from_model(&model).collect::<Vec<_>>();
+    let name      = asset_name(&(data.0).0);
+    let mut stats = MeshStats::default();
 
-                new_mesh(factory_impl, &verts, &model.mesh.indices)
-            };
+    let objects = (data.0).0.drain(..).filter_map(|model| {
+        let mesh = {
+            let (verts, indices, mesh_stats) = verts_from_model(&model, mode);
+            stats.add(&mesh_stats);
 
-            assets.add_asset(&model.name, mesh);
+            assets.get_loader_mut::<FactoryImpl>()
+                .ok_or(LoaderError::Upload("no factory".into()))
+                .and_then(|f| new_mesh(f, &verts, &indices))
+        };
+
+        let mesh = match mesh {
+            Ok(mesh) => mesh,
+            Err(e)   => return error::report(&model.name, e),
+        };
 
-            let mat = model.mesh.material
-                .and_then(|m| materials.get(&m))
-                .map(|m| &m.diffuse_texture)
-                .and_then(|tex| tex.rsplitn(2, '.').skip(1).next())
-                .map(|m| m as &str)
-                .unwrap_or("default");
+        let mesh_id = assets.add_asset(&model.name, mesh);
 
-            Renderable::new(
+        let mat = texture_for(&model, &materials);
+
+        let obj = ObjObject {
+            renderable: Renderable::new(
                 &model.name,
                 mat,
                 mat,
-            )
-        }).collect::<Vec<_>>().into()
-    }
-}
-
-fn verts_from_model<'a>(model: &'a Model) ->
-    impl Iterator<Item=VertexPosNormal> + 'a
-{
-    model.mesh.positions.chunks(3)
-        .zip(model.mesh.normals.chunks(3))
-        .zip(model.mesh.texcoords.chunks(2))
-        .map(
-            |((pos, norms), coords)|
-            VertexPosNormal {
-                pos: [pos[0], pos[1], pos[2]],
-                normal: [norms[0], norms[1], norms[2]],
-                tex_coord: [coords[0], coords[1]],
-            }
-        )
+            ),
+            mesh: mesh_id,
+        };
+
+        (model.name.clone(), obj).into()
+    }).collect::<Vec<_>>();
+
+    stats.log(&name);
+
+    if objects.is_empty() {
+        return error::report(
+            "obj",
+            LoaderError::parse(None, "no usable objects"),
+        );
+    }
+
+    objects.into()
+}
+
+fn verts_from_model(
+    model: &Model,
+    mode: NormalMode,
+) -> (Vec<VertexPosNormal>, Vec<u32>, MeshStats) {
+    let (verts, indices) = vertex::assemble(
+        &model.mesh.positions,
+        &model.mesh.normals,
+        &model.mesh.texcoords,
+        &model.mesh.indices,
+        mode,
+    );
+
+    optimize::optimize(verts, indices)
 }
 
 impl AssetLoader<Mesh> for ObjLoader {
@@ -144,51 +273,143 @@ impl AssetLoader<Mesh> for ObjLoader {
         let mut out_verts   = vec![];
         let mut out_indices = vec![];
 
-        // TODO: Should I collapse textures together? The alternative, I
-        //       guess, is to make this return a Renderable.
+        // Textures are dropped here, load a `MeshBatch` to keep them.
+
+        let mode = assets.get_loader_mut::<NormalMode>()
+            .map(|m| *m)
+            .unwrap_or_default();
+
+        let name      = asset_name(&(data.0).0);
+        let mut stats = MeshStats::default();
 
         for model in (data.0).0.drain(..) {
-            let vertices = verts_from_model(&model);
+            let (vertices, indices, mesh_stats) =
+                verts_from_model(&model, mode);
+            stats.add(&mesh_stats);
 
             let offset = out_verts.len() as u32;
             out_indices.extend(
-                model.mesh.indices.iter().map(|i| i + offset)
+                indices.iter().map(|i| i + offset)
             );
             out_verts.extend(vertices);
+        }
+
+        stats.log(&name);
 
+        let mesh = assets.get_loader_mut::<FactoryImpl>()
+            .ok_or(LoaderError::Upload("no factory".into()))
+            .and_then(|f| new_mesh(f, &out_verts, &out_indices));
+
+        match mesh {
+            Ok(mesh) => Some(mesh),
+            Err(e)   => error::report("obj", e),
         }
+    }
+}
+
+impl AssetLoader<MeshBatch> for ObjLoader {
+    fn from_data(_assets: &mut Assets, _data: Self) -> Option<MeshBatch> {
+        error::report("obj", needs_manager("mesh batches"))
+    }
+
+    fn load_from_data(
+        assets: &mut AssetManager,
+        mut data: Self,
+    ) -> Option<MeshBatch> {
+        let materials = collect_materials(assets, (data.0).1.drain(..));
+        let models    = (data.0).0;
+        let mode      = assets.get_loader_mut::<NormalMode>()
+            .map(|m| *m)
+            .unwrap_or_default();
 
-        let factory_impl = assets.get_loader_mut::<FactoryImpl>()
-            .expect("Unable to retrieve factory");
+        let name = asset_name(&models);
 
-        Some(new_mesh(factory_impl, &out_verts, &out_indices))
+        // Group by texture rather than material name, since materials that
+        // only differ in unsupported properties would draw identically.
+        let mut groups: Vec<(String, Vec<&Model>)> = vec![];
+
+        for model in &models {
+            let tex = texture_for(model, &materials);
+
+            match groups.iter().position(|g| g.0 == tex) {
+                Some(i) => groups[i].1.push(model),
+                None    => groups.push((tex.to_string(), vec![model])),
+            }
+        }
+
+        let mut out_verts   = vec![];
+        let mut out_indices = vec![];
+        let mut ranges      = vec![];
+        let mut stats       = MeshStats::default();
+
+        for &(_, ref group) in &groups {
+            let start = out_indices.len() as u32;
+
+            for model in group {
+                let (vertices, indices, mesh_stats) =
+                    verts_from_model(model, mode);
+                stats.add(&mesh_stats);
+
+                let offset = out_verts.len() as u32;
+                out_indices.extend(indices.iter().map(|i| i + offset));
+                out_verts.extend(vertices);
+            }
+
+            ranges.push((start, out_indices.len() as u32));
+        }
+
+        stats.log(&name);
+
+        let meshes = assets.get_loader_mut::<FactoryImpl>()
+            .ok_or(LoaderError::Upload("no factory".into()))
+            .and_then(
+                |f| new_batch_meshes(f, &out_verts, &out_indices, &ranges)
+            );
+
+        let meshes = match meshes {
+            Ok(meshes) => meshes,
+            Err(e)     => return error::report("obj", e),
+        };
+
+        let ranges = groups.iter()
+            .zip(ranges)
+            .zip(meshes)
+            .map(|((&(ref tex, _), (start, end)), mesh)| {
+                let mesh_name = format!("{}#{}", name, tex);
+                assets.add_asset(&mesh_name, mesh);
+
+                DrawRange {
+                    material:   tex.clone(),
+                    start:      start,
+                    end:        end,
+                    renderable: Renderable::new(&mesh_name, tex, tex),
+                }
+            })
+            .collect();
+
+        MeshBatch { ranges: ranges }.into()
     }
 }
 
-fn new_mesh<F: ::std::ops::DerefMut<Target=FactoryImpl>>(
+pub fn new_mesh<F: ::std::ops::DerefMut<Target=FactoryImpl>>(
     mut factory_impl: F,
     buf: &[VertexPosNormal],
     slc: &[u32],
-) -> Mesh {
-    use gfx::traits::FactoryExt;
-
+) -> Result<Mesh, LoaderError> {
     match *factory_impl {
         FactoryImpl::OpenGL { ref mut factory } => {
-            let (buffer, slice) =
-                factory.create_vertex_buffer_with_slice(
-                    buf,
-                    slc,
-                );
+            let (buffer, slice) = optimize::create_buffer(factory, buf, slc);
 
-            Mesh {
+            Ok(Mesh {
                 mesh_impl: MeshImpl::OpenGL {
                     buffer: buffer,
                     slice: slice,
                 }
-            }
+            })
         }
         #[cfg(windows)]
-        FactoryImpl::Direct3D {} => unimplemented!(),
-        FactoryImpl::Null => Mesh { mesh_impl: MeshImpl::Null },
+        FactoryImpl::Direct3D {} =>
+            Err(LoaderError::UnsupportedFormat("Direct3D meshes".into())),
+        FactoryImpl::Null => Ok(Mesh { mesh_impl: MeshImpl::Null }),
     }
 }
@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use gfx::Slice;
+use gfx::handle::Buffer;
+use gfx_device_gl::{Factory, Resources};
+use amethyst::renderer::VertexPosNormal;
+
+// Roughly what current GPUs keep around post-transform
+const CACHE_SIZE: usize = 32;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MeshStats {
+    pub vertices_before: usize,
+    pub vertices_after:  usize,
+    pub triangles:       usize,
+    pub index_bits:      u8,
+    pub acmr_before:     f32,
+    pub acmr_after:      f32,
+}
+
+impl MeshStats {
+    // Sums up the meshes of one asset. ACMR is per triangle, so it's weighted
+    // by each mesh's triangle count.
+    pub fn add(&mut self, other: &MeshStats) {
+        let (n, m)   = (self.triangles as f32, other.triangles as f32);
+        let weighted = |a: f32, b: f32| if n + m == 0. {
+            0.
+        } else {
+            (a * n + b * m) / (n + m)
+        };
+
+        self.acmr_before = weighted(self.acmr_before, other.acmr_before);
+        self.acmr_after  = weighted(self.acmr_after, other.acmr_after);
+
+        self.vertices_before += other.vertices_before;
+        self.vertices_after  += other.vertices_after;
+        self.triangles       += other.triangles;
+        self.index_bits       = ::std::cmp::max(
+            self.index_bits,
+            other.index_bits,
+        );
+    }
+
+    // One line per loaded asset, in debug builds only
+    pub fn log(&self, asset: &str) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+
+        let _ = writeln!(
+            io::stderr(),
+            "[mesh] {}: {} -> {} vertices, {} triangles, u{} indices, \
+             ACMR {:.3} -> {:.3}",
+            asset,
+            self.vertices_before,
+            self.vertices_after,
+            self.triangles,
+            self.index_bits,
+            self.acmr_before,
+            self.acmr_after
+        );
+    }
+}
+
+pub fn index_bits(vertex_count: usize) -> u8 {
+    if vertex_count <= ::std::u16::MAX as usize + 1 { 16 } else { 32 }
+}
+
+// Deduplicates vertices, reorders triangles for the post-transform cache and
+// then vertices in the order they're first used, returning what changed.
+pub fn optimize(
+    verts: Vec<VertexPosNormal>,
+    indices: Vec<u32>,
+) -> (Vec<VertexPosNormal>, Vec<u32>, MeshStats) {
+    let vertices_before = verts.len();
+    let acmr_before     = acmr(&indices, CACHE_SIZE);
+
+    let (verts, indices) = dedup(verts, &indices);
+    let indices          = reorder_triangles(&indices, verts.len());
+    let (verts, indices) = reorder_vertices(verts, &indices);
+
+    let stats = MeshStats {
+        vertices_before: vertices_before,
+        vertices_after:  verts.len(),
+        triangles:       indices.len() / 3,
+        index_bits:      index_bits(verts.len()),
+        acmr_before:     acmr_before,
+        acmr_after:      acmr(&indices, CACHE_SIZE),
+    };
+
+    (verts, indices, stats)
+}
+
+fn key(v: &VertexPosNormal) -> [u32; 8] {
+    let bits = |f: f32| f.to_bits();
+
+    [
+        bits(v.pos[0]), bits(v.pos[1]), bits(v.pos[2]),
+        bits(v.normal[0]), bits(v.normal[1]), bits(v.normal[2]),
+        bits(v.tex_coord[0]), bits(v.tex_coord[1]),
+    ]
+}
+
+pub fn dedup(
+    verts: Vec<VertexPosNormal>,
+    indices: &[u32],
+) -> (Vec<VertexPosNormal>, Vec<u32>) {
+    let mut seen  = HashMap::with_capacity(verts.len());
+    let mut out   = Vec::with_capacity(verts.len());
+    let mut remap = Vec::with_capacity(verts.len());
+
+    for v in verts {
+        let next = out.len() as u32;
+        let id   = *seen.entry(key(&v)).or_insert(next);
+
+        if id == next {
+            out.push(v);
+        }
+
+        remap.push(id);
+    }
+
+    (out, indices.iter().map(|&i| remap[i as usize]).collect())
+}
+
+// Average cache miss ratio: transformed vertices per triangle with a FIFO cache
+// of `cache_size`. 0.5 is ideal for large grids, 3 is the worst possible.
+pub fn acmr(indices: &[u32], cache_size: usize) -> f32 {
+    let triangles = indices.len() / 3;
+
+    if triangles == 0 {
+        return 0.;
+    }
+
+    let mut cache  = Vec::with_capacity(cache_size);
+    let mut misses = 0;
+
+    for &i in indices {
+        if !cache.contains(&i) {
+            misses += 1;
+
+            if cache.len() == cache_size {
+                cache.remove(0);
+            }
+            cache.push(i);
+        }
+    }
+
+    misses as f32 / triangles as f32
+}
+
+// Greedy triangle ordering: always emit the unemitted triangle touching the
+// most vertices still in a simulated FIFO cache, only falling back to the next
+// triangle in file order when nothing in the cache is usable.
+pub fn reorder_triangles(indices: &[u32], vertex_count: usize) -> Vec<u32> {
+    let tris = indices.chunks(3).filter(|t| t.len() == 3).collect::<Vec<_>>();
+
+    let mut adjacency = vec![vec![]; vertex_count];
+    for (t, tri) in tris.iter().enumerate() {
+        for &v in tri.iter() {
+            adjacency[v as usize].push(t);
+        }
+    }
+
+    let mut emitted  = vec![false; tris.len()];
+    let mut cache    = Vec::with_capacity(CACHE_SIZE);
+    let mut out      = Vec::with_capacity(indices.len());
+    let mut fallback = 0;
+
+    for _ in 0..tris.len() {
+        let best = cache.iter()
+            .flat_map(|&v: &u32| adjacency[v as usize].iter().cloned())
+            .filter(|&t| !emitted[t])
+            .max_by_key(
+                |&t| tris[t].iter().filter(|v| cache.contains(*v)).count()
+            );
+
+        let t = match best {
+            Some(t) => t,
+            None    => {
+                while emitted[fallback] {
+                    fallback += 1;
+                }
+                fallback
+            },
+        };
+
+        emitted[t] = true;
+
+        for &v in tris[t].iter() {
+            out.push(v);
+
+            if !cache.contains(&v) {
+                if cache.len() == CACHE_SIZE {
+                    cache.remove(0);
+                }
+                cache.push(v);
+            }
+        }
+    }
+
+    out
+}
+
+pub fn reorder_vertices(
+    verts: Vec<VertexPosNormal>,
+    indices: &[u32],
+) -> (Vec<VertexPosNormal>, Vec<u32>) {
+    let mut remap = vec![None; verts.len()];
+    let mut order = Vec::with_capacity(verts.len());
+
+    let indices = indices.iter().map(|&i| {
+        if let Some(new) = remap[i as usize] {
+            return new;
+        }
+
+        let new = order.len() as u32;
+        order.push(i as usize);
+        remap[i as usize] = Some(new);
+
+        new
+    }).collect();
+
+    let mut slots = verts.into_iter().map(Some).collect::<Vec<_>>();
+    let verts     = order.iter().filter_map(|&i| slots[i].take()).collect();
+
+    (verts, indices)
+}
+
+// Uploads with 16-bit indices whenever the vertex count allows it
+pub fn create_buffer(
+    factory: &mut Factory,
+    buf: &[VertexPosNormal],
+    slc: &[u32],
+) -> (Buffer<Resources, VertexPosNormal>, Slice<Resources>) {
+    use gfx::traits::FactoryExt;
+
+    if index_bits(buf.len()) == 16 {
+        let slc = slc.iter().map(|&i| i as u16).collect::<Vec<_>>();
+
+        factory.create_vertex_buffer_with_slice(buf, &slc[..])
+    } else {
+        factory.create_vertex_buffer_with_slice(buf, slc)
+    }
+}
@@ -0,0 +1,363 @@
+use amethyst::context::asset_manager::{
+    Asset,
+    Assets,
+    AssetManager,
+    AssetLoader,
+    AssetLoaderRaw,
+};
+use amethyst::ecs::{Entity, World};
+use amethyst::processors::rendering::Renderable;
+use amethyst::processors::transform::{Child, LocalTransform, Transform};
+use yaml_rust::Yaml;
+
+use systems::physics::{quaternion_angle, BodyDesc, Collider};
+use systems::physics::{ImpulseComponent, PhysicsComponent};
+use super::{load, load_object, yaml, Dependencies, Parse};
+use super::error::{self, LoaderError};
+
+// Prefabs describe a tree of entities in YAML, the same way the config files
+// are written:
+//
+//     transform:
+//         translation: [0, 0, 0]
+//         rotation: [1, 0, 0, 0]      # w, x, y, z
+//         scale: [1, 1, 1]
+//     physics:                        # only allowed on the root
+//         shape: box                  # box (with `size`) or ball (`radius`)
+//         size: [0.926, 0.926]
+//         dynamic: true
+//         density: 0.5
+//         restitution: 0.5
+//         friction: 0.9
+//     impulse: true                   # receives `ImpulseComponent`s
+//     children:
+//         - transform: { translation: [-0.5, -0.5, 0] }
+//           renderable: quad#Quad_face
+//
+// `renderable` is either an object as given to `loaders::load_object`, or
+// `{ mesh, texture }` (or `{ mesh, ambient, diffuse }`) naming loaded assets.
+#[derive(Clone, Debug)]
+pub struct Prefab {
+    yaml: Yaml,
+}
+
+#[derive(Clone, Debug)]
+enum Model {
+    Object(String),
+    Parts {
+        mesh:    String,
+        ambient: String,
+        diffuse: String,
+    },
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    translation: [f32; 3],
+    rotation:    [f32; 4],
+    scale:       [f32; 3],
+    model:       Option<Model>,
+    body:        Option<BodyDesc>,
+    impulse:     bool,
+    children:    Vec<Node>,
+}
+
+fn model(yaml: &Yaml) -> Result<Option<Model>, LoaderError> {
+    match *yaml {
+        Yaml::BadValue | Yaml::Null => Ok(None),
+        Yaml::String(ref object)    => Ok(Some(Model::Object(object.clone()))),
+        Yaml::Hash(_)               => {
+            let mesh    = yaml::require(yaml, "mesh", Yaml::as_str)?;
+            let texture = yaml["texture"].as_str().unwrap_or("default");
+
+            Ok(Some(Model::Parts {
+                mesh:    mesh.into(),
+                ambient: yaml["ambient"].as_str().unwrap_or(texture).into(),
+                diffuse: yaml["diffuse"].as_str().unwrap_or(texture).into(),
+            }))
+        },
+        _ => Err(LoaderError::parse(None, "invalid value for `renderable`")),
+    }
+}
+
+pub fn parse_body(yaml: &Yaml) -> Result<Option<BodyDesc>, LoaderError> {
+    if let Yaml::BadValue = *yaml {
+        return Ok(None);
+    }
+
+    let default = BodyDesc::default();
+
+    let collider = match yaml["shape"].as_str() {
+        None | Some("box") => Collider::Box(
+            yaml::pair(&yaml["size"]).unwrap_or([1., 1.])
+        ),
+        Some("ball") => Collider::Ball(
+            yaml::number(&yaml["radius"]).unwrap_or(0.5)
+        ),
+        Some(other) => return Err(LoaderError::parse(
+            None,
+            format!("unknown physics shape `{}`", other),
+        )),
+    };
+
+    Ok(Some(BodyDesc {
+        collider:    collider,
+        dynamic:     yaml["dynamic"].as_bool().unwrap_or(default.dynamic),
+        density:     yaml::number(&yaml["density"]).unwrap_or(default.density),
+        restitution:
+            yaml::number(&yaml["restitution"]).unwrap_or(default.restitution),
+        friction:
+            yaml::number(&yaml["friction"]).unwrap_or(default.friction),
+    }))
+}
+
+pub fn body_to_yaml(body: &BodyDesc) -> Yaml {
+    let mut pairs = match body.collider {
+        Collider::Box(size) => vec![
+            ("shape", Yaml::String("box".into())),
+            ("size", yaml::reals(&size)),
+        ],
+        Collider::Ball(radius) => vec![
+            ("shape", Yaml::String("ball".into())),
+            ("radius", yaml::real(radius)),
+        ],
+    };
+
+    pairs.push(("dynamic", Yaml::Boolean(body.dynamic)));
+    pairs.push(("density", yaml::real(body.density)));
+    pairs.push(("restitution", yaml::real(body.restitution)));
+    pairs.push(("friction", yaml::real(body.friction)));
+
+    yaml::hash(pairs)
+}
+
+// Translation, rotation and scale, defaulting to the identity
+pub fn parse_transform(
+    yaml: &Yaml,
+) -> Result<([f32; 3], [f32; 4], [f32; 3]), LoaderError> {
+    let rotation = match yaml::numbers(&yaml["rotation"]) {
+        Some(ref r) if r.len() == 4 => [r[0], r[1], r[2], r[3]],
+        Some(_) => return Err(
+            LoaderError::parse(None, "rotation must be [w, x, y, z]")
+        ),
+        None => [1., 0., 0., 0.],
+    };
+
+    Ok((
+        yaml::triple(&yaml["translation"]).unwrap_or([0.; 3]),
+        rotation,
+        yaml::triple(&yaml["scale"]).unwrap_or([1.; 3]),
+    ))
+}
+
+fn node(yaml: &Yaml, root: bool) -> Result<Node, LoaderError> {
+    let body = parse_body(&yaml["physics"])?;
+
+    // Bodies are simulated in world space, which only matches the local
+    // transform of an entity without a parent.
+    if body.is_some() && !root {
+        return Err(LoaderError::parse(
+            None,
+            "only the root of a prefab can have a physics body",
+        ));
+    }
+
+    let (translation, rotation, scale) = parse_transform(&yaml["transform"])?;
+
+    let empty = vec![];
+
+    Ok(Node {
+        translation: translation,
+        rotation:    rotation,
+        scale:       scale,
+        model:       model(&yaml["renderable"])?,
+        body:        body,
+        impulse:     yaml["impulse"].as_bool().unwrap_or(false),
+        children:    yaml["children"].as_vec().unwrap_or(&empty).iter()
+            .map(|child| node(child, false))
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+impl Node {
+    // Resolves every renderable up front, depth first, so a missing model
+    // can't leave half a prefab in the world.
+    fn renderables(
+        &self,
+        assets: &mut AssetManager,
+        out: &mut Vec<Option<Renderable>>,
+    ) -> Result<(), LoaderError> {
+        out.push(match self.model {
+            Some(Model::Object(ref path)) =>
+                Some(load_object(assets, path, "obj")?),
+            Some(Model::Parts { ref mesh, ref ambient, ref diffuse }) =>
+                Some(Renderable::new(mesh, ambient, diffuse)),
+            None => None,
+        });
+
+        for child in &self.children {
+            child.renderables(assets, out)?;
+        }
+
+        Ok(())
+    }
+
+    fn spawn<I>(
+        &self,
+        world: &mut World,
+        parent: Option<Entity>,
+        offset: [f32; 2],
+        renderables: &mut I,
+    ) -> Entity
+        where I: Iterator<Item = Option<Renderable>>
+    {
+        let translation = [
+            self.translation[0] + offset[0],
+            self.translation[1] + offset[1],
+            self.translation[2],
+        ];
+
+        let mut l_trans = LocalTransform::default();
+        l_trans.translation = translation;
+        l_trans.rotation    = self.rotation;
+        l_trans.scale       = self.scale;
+
+        let mut builder = world.create_now()
+            .with(l_trans)
+            .with(Transform::default());
+
+        if let Some(parent) = parent {
+            builder = builder.with(Child::new(parent));
+        }
+
+        if let Some(body) = self.body {
+            builder = builder.with(
+                PhysicsComponent::from_desc(
+                    body,
+                    [translation[0], translation[1]],
+                ).rotated(quaternion_angle(self.rotation))
+            );
+        }
+
+        if self.impulse {
+            builder = builder.with(ImpulseComponent::default());
+        }
+
+        if let Some(Some(renderable)) = renderables.next() {
+            builder = builder.with(renderable);
+        }
+
+        let entity = builder.build();
+
+        for child in &self.children {
+            child.spawn(world, Some(entity), [0.; 2], renderables);
+        }
+
+        entity
+    }
+}
+
+impl Prefab {
+    pub fn spawn(
+        &self,
+        assets: &mut AssetManager,
+        world: &mut World,
+        position: [f32; 2],
+    ) -> Result<Entity, LoaderError> {
+        self.spawn_with(assets, world, position, &Yaml::Null)
+    }
+
+    // `overrides` is merged over the prefab (see `yaml::merge`) before it's
+    // spawned, e.g. `{ physics: { dynamic: true }, impulse: true }`.
+    pub fn spawn_with(
+        &self,
+        assets: &mut AssetManager,
+        world: &mut World,
+        position: [f32; 2],
+        overrides: &Yaml,
+    ) -> Result<Entity, LoaderError> {
+        let root = node(&yaml::merge(&self.yaml, overrides), true)?;
+
+        let mut renderables = vec![];
+        root.renderables(assets, &mut renderables)?;
+
+        Ok(root.spawn(world, None, position, &mut renderables.into_iter()))
+    }
+}
+
+// Loads (if it isn't already) and spawns a prefab, see `Prefab::spawn_with`
+pub fn spawn_prefab(
+    assets: &mut AssetManager,
+    world: &mut World,
+    name: &str,
+    position: [f32; 2],
+    overrides: &Yaml,
+) -> Result<Entity, LoaderError> {
+    let id     = load::<Prefab>(assets, name, "prefab")?;
+    let prefab = {
+        let prefabs = assets.read_assets();
+        let prefab: Option<&Asset<Prefab>> = prefabs.get(id);
+
+        prefab.map(|p| p.0.clone())
+            .ok_or_else(|| LoaderError::missing(name, "prefab"))?
+    };
+
+    prefab.spawn_with(assets, world, position, overrides)
+}
+
+pub struct PrefabLoader {
+    yaml: Yaml,
+}
+
+impl Parse for PrefabLoader {
+    fn parse(data: &[u8]) -> Result<Self, LoaderError> {
+        let yaml = yaml::parse(data)?;
+
+        // Catches mistakes at load time rather than on the first spawn
+        node(&yaml, true)?;
+
+        Ok(PrefabLoader {
+            yaml: yaml,
+        })
+    }
+}
+
+fn object_files(yaml: &Yaml, out: &mut Vec<String>) {
+    if let Some(object) = yaml["renderable"].as_str() {
+        let file = format!("{}.obj", object.splitn(2, '#').next().unwrap());
+
+        if !out.contains(&file) {
+            out.push(file);
+        }
+    }
+
+    for child in yaml["children"].as_vec().map_or(&[][..], |c| &c[..]) {
+        object_files(child, out);
+    }
+}
+
+impl Dependencies for PrefabLoader {
+    fn dependencies(&self) -> Vec<String> {
+        let mut deps = vec![];
+        object_files(&self.yaml, &mut deps);
+
+        deps
+    }
+}
+
+impl AssetLoaderRaw for PrefabLoader {
+    fn from_raw(_assets: &Assets, data: &[u8]) -> Option<Self> {
+        match Self::parse(data) {
+            Ok(loader) => Some(loader),
+            Err(e)     => error::report("prefab", e),
+        }
+    }
+}
+
+impl AssetLoader<Prefab> for PrefabLoader {
+    fn from_data(_assets: &mut Assets, data: Self) -> Option<Prefab> {
+        Some(Prefab {
+            yaml: data.yaml,
+        })
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+use amethyst::context::asset_manager::{
+    Assets,
+    AssetManager,
+    AssetLoader,
+    AssetLoaderRaw,
+    FactoryImpl,
+    Texture,
+};
+use amethyst::processors::rendering::Renderable;
+use amethyst::renderer::VertexPosNormal;
+use yaml_rust::Yaml;
+
+use super::{Dependencies, Parse};
+use super::error::{self, LoaderError};
+use super::obj::new_mesh;
+use super::yaml;
+
+// Texture coordinates of one frame, in the same orientation as quad.obj's
+// (`bottom` is v = 0)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UvRect {
+    pub left:   f32,
+    pub right:  f32,
+    pub bottom: f32,
+    pub top:    f32,
+}
+
+impl UvRect {
+    // `x`, `y`, `w` and `h` are in pixels, measured from the image's top-left
+    // corner like every image editor does.
+    pub fn from_pixels(rect: [f32; 4], image: [f32; 2]) -> Self {
+        let (x, y, w, h) = (rect[0], rect[1], rect[2], rect[3]);
+
+        UvRect {
+            left:   x / image[0],
+            right:  (x + w) / image[0],
+            bottom: 1. - (y + h) / image[1],
+            top:    1. - y / image[1],
+        }
+    }
+
+    pub fn map(&self, uv: [f32; 2]) -> [f32; 2] {
+        [
+            self.left + uv[0] * (self.right - self.left),
+            self.bottom + uv[1] * (self.top - self.bottom),
+        ]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlayMode {
+    Loop,
+    Once,
+}
+
+#[derive(Clone, Debug)]
+pub struct Clip {
+    pub frames:     Vec<usize>,
+    pub frame_time: f32,
+    pub mode:       PlayMode,
+}
+
+impl Clip {
+    pub fn duration(&self) -> f32 {
+        self.frames.len() as f32 * self.frame_time
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SpriteSheet {
+    pub texture: String,
+    // Prefix of the frame meshes' names, unique to this sheet
+    pub meshes:  String,
+    pub frames:  Vec<UvRect>,
+    pub clips:   HashMap<String, Clip>,
+}
+
+impl SpriteSheet {
+    pub fn frame_mesh(&self, frame: usize) -> String {
+        format!("{}#{}", self.meshes, frame % self.frames.len())
+    }
+
+    pub fn renderable(&self, frame: usize) -> Renderable {
+        Renderable::new(&self.frame_mesh(frame), &self.texture, &self.texture)
+    }
+}
+
+// Sprite sheets are described in YAML, next to the image they cut up:
+//
+//     texture: deer.png
+//     size: [128, 64]
+//     grid: [32, 32]          # either cut the image into equal cells...
+//     frames:                 # ...or list [x, y, w, h] rectangles by hand
+//         - [0, 0, 32, 32]
+//     clips:
+//         idle: { frames: [0, 1], fps: 4 }
+//         dead: { frames: [6, 7], fps: 8, mode: once }
+pub struct SpriteSheetLoader {
+    texture: (String, String),
+    frames:  Vec<UvRect>,
+    clips:   HashMap<String, Clip>,
+}
+
+impl Parse for SpriteSheetLoader {
+    fn parse(data: &[u8]) -> Result<Self, LoaderError> {
+        let doc = yaml::parse(data)?;
+
+        let texture = yaml::require(&doc, "texture", Yaml::as_str)?;
+        let size    = yaml::require(&doc, "size", yaml::pair)?;
+
+        let mut split = texture.rsplitn(2, '.');
+        let texture   = match (split.next(), split.next()) {
+            (Some(ext), Some(name)) => (name.to_string(), ext.to_string()),
+            (Some(name), None)      => (name.to_string(), "png".to_string()),
+            _                       => unreachable!(),
+        };
+
+        let frames = if let Some(grid) = yaml::pair(&doc["grid"]) {
+            // Anything smaller than a pixel (or not a number) would make the
+            // frame count below unbounded
+            if !grid.iter().chain(&size).all(|&n| n >= 1. && n.is_finite()) {
+                return Err(LoaderError::parse(
+                    None,
+                    "size and grid must be at least one pixel",
+                ));
+            }
+
+            let (cols, rows) = (
+                (size[0] / grid[0]) as usize,
+                (size[1] / grid[1]) as usize,
+            );
+
+            (0..rows).flat_map(
+                |r| (0..cols).map(move |c| UvRect::from_pixels(
+                    [c as f32 * grid[0], r as f32 * grid[1], grid[0], grid[1]],
+                    size,
+                ))
+            ).collect()
+        } else {
+            let frames = yaml::require(&doc, "frames", Yaml::as_vec)?;
+
+            frames.iter().map(|f| match yaml::numbers(f) {
+                Some(ref f) if f.len() == 4 => Ok(
+                    UvRect::from_pixels([f[0], f[1], f[2], f[3]], size)
+                ),
+                _ => Err(
+                    LoaderError::parse(None, "frames must be [x, y, w, h]")
+                ),
+            }).collect::<Result<Vec<_>, _>>()?
+        };
+
+        if frames.is_empty() {
+            return Err(LoaderError::parse(None, "sprite sheet has no frames"));
+        }
+
+        let clips = match doc["clips"].as_hash() {
+            Some(clips) => clips.iter().map(
+                |(name, clip)| {
+                    let name = name.as_str().ok_or(
+                        LoaderError::parse(None, "clip names must be strings")
+                    )?;
+
+                    Ok((name.to_string(), parse_clip(clip, frames.len())?))
+                }
+            ).collect::<Result<HashMap<_, _>, _>>()?,
+            None => HashMap::new(),
+        };
+
+        Ok(SpriteSheetLoader {
+            texture: texture,
+            frames:  frames,
+            clips:   clips,
+        })
+    }
+}
+
+fn parse_clip(clip: &Yaml, frame_count: usize) -> Result<Clip, LoaderError> {
+    let frames = yaml::require(clip, "frames", yaml::numbers)?;
+
+    // Casting would quietly turn -1 into 0 and 1.5 into 1
+    if frames.iter().any(|&f| f < 0. || f.fract() != 0.) {
+        return Err(LoaderError::parse(
+            None,
+            "clip frames must be whole, non-negative numbers",
+        ));
+    }
+
+    let frames = frames.iter().map(|&f| f as usize).collect::<Vec<_>>();
+
+    if frames.is_empty() || frames.iter().any(|&f| f >= frame_count) {
+        return Err(LoaderError::parse(None, "clip frame out of range"));
+    }
+
+    let fps        = yaml::number(&clip["fps"]).unwrap_or(10.);
+    let frame_time = 1. / fps;
+
+    // Zero or negative frame times would never advance, or go backwards
+    if !(frame_time > 0. && frame_time.is_finite()) {
+        return Err(LoaderError::parse(None, "clip fps must be positive"));
+    }
+
+    let mode = match clip["mode"].as_str() {
+        None | Some("loop") => PlayMode::Loop,
+        Some("once")        => PlayMode::Once,
+        Some(other)         => return Err(LoaderError::parse(
+            None,
+            format!("unknown clip mode `{}`", other),
+        )),
+    };
+
+    Ok(Clip {
+        frames:     frames,
+        frame_time: frame_time,
+        mode:       mode,
+    })
+}
+
+impl Dependencies for SpriteSheetLoader {
+    fn dependencies(&self) -> Vec<String> {
+        vec![format!("{}.{}", self.texture.0, self.texture.1)]
+    }
+}
+
+impl AssetLoaderRaw for SpriteSheetLoader {
+    fn from_raw(_assets: &Assets, data: &[u8]) -> Option<Self> {
+        match Self::parse(data) {
+            Ok(loader) => Some(loader),
+            Err(e)     => error::report("sheet", e),
+        }
+    }
+}
+
+impl AssetLoader<SpriteSheet> for SpriteSheetLoader {
+    fn from_data(_assets: &mut Assets, _data: Self) -> Option<SpriteSheet> {
+        error::report(
+            "sheet",
+            LoaderError::UnsupportedFormat(
+                "sprite sheets must be loaded through the asset manager".into()
+            ),
+        )
+    }
+
+    fn load_from_data(
+        assets: &mut AssetManager,
+        data: Self,
+    ) -> Option<SpriteSheet> {
+        let (ref name, ref ext) = data.texture;
+
+        let loaded = assets.id_from_name(name)
+            .map(|id| assets.read_assets::<Texture>().get(id).is_some())
+            .unwrap_or(false);
+
+        if !loaded {
+            return error::report("sheet", LoaderError::missing(name, ext));
+        }
+
+        // Sheets can share a texture but cut it up differently, so the frame
+        // meshes are named after the sheet rather than the texture
+        static SHEETS: AtomicUsize = ATOMIC_USIZE_INIT;
+        let sheet_id = SHEETS.fetch_add(1, Ordering::Relaxed);
+
+        let sheet = SpriteSheet {
+            texture: name.clone(),
+            meshes:  format!("sheet{}", sheet_id),
+            frames:  data.frames,
+            clips:   data.clips,
+        };
+
+        // Every frame gets its own copy of the unit quad from quad.obj with the
+        // texture coordinates baked in, so a plain `Renderable` can show it.
+        for (i, rect) in sheet.frames.iter().enumerate() {
+            let verts = [[0., 1.], [0., 0.], [1., 0.], [1., 1.]].iter().map(
+                |corner| VertexPosNormal {
+                    pos: [corner[0], corner[1], 0.],
+                    normal: [0., 0., 1.],
+                    tex_coord: rect.map(*corner),
+                }
+            ).collect::<Vec<_>>();
+
+            let mesh = assets.get_loader_mut::<FactoryImpl>()
+                .ok_or(LoaderError::Upload("no factory".into()))
+                .and_then(|f| new_mesh(f, &verts, &[0, 1, 2, 0, 2, 3]));
+
+            match mesh {
+                Ok(mesh) => { assets.add_asset(&sheet.frame_mesh(i), mesh); },
+                Err(e)   => return error::report("sheet", e),
+            }
+        }
+
+        sheet.into()
+    }
+}
@@ -1,6 +1,11 @@
-use gfx::tex::AaMode;
+use std::marker::PhantomData;
+use std::ops::DerefMut;
+
+use gfx::tex::{AaMode, Kind};
 use image::{
     self,
+    imageops,
+    FilterType,
     RgbaImage,
     ImageFormat,
 };
@@ -8,41 +13,393 @@ use amethyst::context::asset_manager::{
     Assets,
     AssetLoader,
     AssetLoaderRaw,
+    FactoryImpl,
     Texture,
-    RawTextureData,
-    Kind,
+    TextureImpl,
 };
+use amethyst::renderer;
 
-// TODO: Make this generic
-pub struct PngTextureLoader(u32, u32, Vec<u8>);
+use super::{dds, Dependencies, Parse};
+use super::error::{self, LoaderError};
 
-impl AssetLoaderRaw for PngTextureLoader {
-    fn from_raw(_assets: &Assets, data: &[u8]) -> Option<Self> {
-        let o_img = image::load_from_memory_with_format(data, ImageFormat::PNG)
-            .map(|dyn| dyn.to_rgba());
+// Registered with `AssetManager::add_loader`, read back every time a texture is
+// uploaded.
+#[derive(Clone, Debug)]
+pub struct TextureOptions {
+    pub srgb:              bool,
+    pub mipmaps:           bool,
+    pub premultiply_alpha: bool,
+}
 
-        let image: RgbaImage =
-            if let Ok(img) = o_img {
-                img
-            } else {
-                return None;
-            };
+impl Default for TextureOptions {
+    fn default() -> Self {
+        TextureOptions {
+            srgb:              true,
+            mipmaps:           false,
+            premultiply_alpha: false,
+        }
+    }
+}
+
+pub trait ImageKind {
+    fn name() -> &'static str;
+    fn decode(data: &[u8]) -> Result<RgbaImage, LoaderError>;
+}
+
+macro_rules! image_kinds {
+    ($($kind:ident => ($name:expr, $fmt:expr)),* $(,)*) => {
+        $(
+            pub struct $kind;
+
+            impl ImageKind for $kind {
+                fn name() -> &'static str { $name }
+
+                fn decode(data: &[u8]) -> Result<RgbaImage, LoaderError> {
+                    image::load_from_memory_with_format(data, $fmt)
+                        .map(|dyn| dyn.to_rgba())
+                        .map_err(|e| LoaderError::parse(None, format!("{}", e)))
+                }
+            }
+        )*
+    }
+}
+
+image_kinds! {
+    Png  => ("png", ImageFormat::PNG),
+    Jpeg => ("jpg", ImageFormat::JPEG),
+    Tga  => ("tga", ImageFormat::TGA),
+    Bmp  => ("bmp", ImageFormat::BMP),
+    Gif  => ("gif", ImageFormat::GIF),
+}
+
+// The image crate can't read DDS and the factory can't upload compressed
+// textures, so BC1-3 blocks are decoded to RGBA8 in software
+pub struct Dds;
+
+impl ImageKind for Dds {
+    fn name() -> &'static str { "dds" }
+
+    fn decode(data: &[u8]) -> Result<RgbaImage, LoaderError> {
+        dds::decode(data)
+    }
+}
+
+// RGBA8 texels ready to upload, with the options already applied
+#[derive(Clone, Debug)]
+pub struct DecodedImage {
+    pub width:  u32,
+    pub height: u32,
+    // Largest first, only one unless mipmaps were asked for
+    pub levels: Vec<Vec<u8>>,
+    pub srgb:   bool,
+}
+
+pub struct ImageTextureLoader<F> {
+    image:   RgbaImage,
+    _format: PhantomData<F>,
+}
+
+pub type PngTextureLoader = ImageTextureLoader<Png>;
+
+impl<F: ImageKind> Parse for ImageTextureLoader<F> {
+    fn parse(data: &[u8]) -> Result<Self, LoaderError> {
+        Ok(ImageTextureLoader {
+            image:   F::decode(data)?,
+            _format: PhantomData,
+        })
+    }
+}
+
+impl<F> ImageTextureLoader<F> {
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.image.dimensions()
+    }
+
+    // Returns every mip level, largest first, with `options` applied.
+    pub fn levels(&self, options: &TextureOptions) -> Vec<RgbaImage> {
+        let mut base = self.image.clone();
+
+        if options.premultiply_alpha {
+            for px in base.pixels_mut() {
+                let a = px[3] as u32;
+
+                for c in 0..3 {
+                    px[c] = ((px[c] as u32 * a + 127) / 255) as u8;
+                }
+            }
+        }
+
+        let mut levels = vec![base];
+
+        if options.mipmaps {
+            loop {
+                let (w, h) = levels[levels.len() - 1].dimensions();
+
+                if w == 1 && h == 1 {
+                    break;
+                }
+
+                let next = imageops::resize(
+                    &levels[levels.len() - 1],
+                    ::std::cmp::max(w / 2, 1),
+                    ::std::cmp::max(h / 2, 1),
+                    FilterType::Triangle,
+                );
+
+                levels.push(next);
+            }
+        }
+
+        levels
+    }
+
+    pub fn raw(&self, options: &TextureOptions) -> DecodedImage {
+        let (w, h) = self.dimensions();
 
-        let (image_w, image_h) = image.dimensions();
+        DecodedImage {
+            width:  w,
+            height: h,
+            levels: self.levels(options)
+                .into_iter()
+                .map(|level| level.into_raw())
+                .collect(),
+            srgb:   options.srgb,
+        }
+    }
+}
 
-        let pixels: Vec<u8> = image.into_vec();
+impl<F> Dependencies for ImageTextureLoader<F> {}
 
-        PngTextureLoader(image_w, image_h, pixels).into()
+impl<F: ImageKind> AssetLoaderRaw for ImageTextureLoader<F> {
+    fn from_raw(_assets: &Assets, data: &[u8]) -> Option<Self> {
+        match Self::parse(data) {
+            Ok(loader) => Some(loader),
+            Err(e)     => error::report(F::name(), e),
+        }
     }
 }
 
-impl AssetLoader<Texture> for PngTextureLoader {
+impl<F: ImageKind> AssetLoader<Texture> for ImageTextureLoader<F> {
     fn from_data(assets: &mut Assets, data: Self) -> Option<Texture> {
-        let tex = RawTextureData {
-            kind: Kind::D2(data.0 as _, data.1 as _, AaMode::Single),
-            raw: &data.2,
-        };
+        let options = assets.get_loader_mut::<TextureOptions>()
+            .map(|o| o.clone())
+            .unwrap_or_default();
+
+        let raw = data.raw(&options);
+
+        let tex = assets.get_loader_mut::<FactoryImpl>()
+            .ok_or(LoaderError::Upload("no factory".into()))
+            .and_then(|f| new_texture(f, &raw));
+
+        match tex {
+            Ok(tex) => Some(tex),
+            Err(e)  => error::report(F::name(), e),
+        }
+    }
+}
+
+fn new_texture<F: DerefMut<Target=FactoryImpl>>(
+    mut factory_impl: F,
+    raw: &DecodedImage,
+) -> Result<Texture, LoaderError> {
+    use gfx::Factory;
+    use gfx::format::{Rgba8, Srgba8};
+
+    let kind   = Kind::D2(raw.width as _, raw.height as _, AaMode::Single);
+    let levels = raw.levels.iter().map(|l| &l[..]).collect::<Vec<_>>();
+    let levels = &levels[..];
+
+    match *factory_impl {
+        FactoryImpl::OpenGL { ref mut factory } => {
+            let view = if raw.srgb {
+                factory.create_texture_const_u8::<Srgba8>(kind, levels)
+                    .map(|(_, view)| view)
+            } else {
+                factory.create_texture_const_u8::<Rgba8>(kind, levels)
+                    .map(|(_, view)| view)
+            }.map_err(|e| LoaderError::Upload(format!("{:?}", e)))?;
+
+            Ok(Texture {
+                texture_impl: TextureImpl::OpenGL {
+                    texture: renderer::Texture::Texture(view),
+                }
+            })
+        }
+        #[cfg(windows)]
+        FactoryImpl::Direct3D {} =>
+            Err(LoaderError::UnsupportedFormat("Direct3D textures".into())),
+        FactoryImpl::Null => Ok(Texture { texture_impl: TextureImpl::Null }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use loaders::Parse;
+
+    // Every fixture is 2x2, in this order, except that bmp and gif can't store
+    // partial alpha and jpg is a single grey
+    const RGBA: [u8; 16] = [
+        255, 0,   0,   255,
+        0,   255, 0,   255,
+        0,   0,   255, 128,
+        255, 255, 255, 0,
+    ];
+    const OPAQUE: [u8; 16] = [
+        255, 0,   0,   255,
+        0,   255, 0,   255,
+        0,   0,   255, 255,
+        255, 255, 255, 255,
+    ];
+
+    fn linear() -> TextureOptions {
+        TextureOptions {
+            srgb:              false,
+            mipmaps:           false,
+            premultiply_alpha: false,
+        }
+    }
+
+    fn decode<F: ImageKind>(
+        data: &[u8],
+        options: &TextureOptions,
+    ) -> DecodedImage {
+        ImageTextureLoader::<F>::parse(data)
+            .unwrap_or_else(|e| panic!("{}: {}", F::name(), e))
+            .raw(options)
+    }
+
+    fn check(raw: &DecodedImage, pixels: &[u8]) {
+        assert_eq!((raw.width, raw.height), (2, 2));
+        assert_eq!(raw.levels.len(), 1);
+        assert_eq!(&raw.levels[0][..], pixels);
+    }
+
+    #[test]
+    fn png() {
+        let data = include_bytes!("../../tests/fixtures/rgba.png");
+
+        check(&decode::<Png>(data, &linear()), &RGBA);
+    }
+
+    #[test]
+    fn tga() {
+        let data = include_bytes!("../../tests/fixtures/rgba.tga");
+
+        check(&decode::<Tga>(data, &linear()), &RGBA);
+    }
+
+    #[test]
+    fn bmp() {
+        let data = include_bytes!("../../tests/fixtures/rgb.bmp");
+
+        check(&decode::<Bmp>(data, &linear()), &OPAQUE);
+    }
+
+    #[test]
+    fn gif() {
+        let data = include_bytes!("../../tests/fixtures/palette.gif");
+
+        check(&decode::<Gif>(data, &linear()), &OPAQUE);
+    }
+
+    #[test]
+    fn jpg() {
+        let data = include_bytes!("../../tests/fixtures/grey.jpg");
+        let raw  = decode::<Jpeg>(data, &linear());
+
+        assert_eq!((raw.width, raw.height), (2, 2));
+
+        // Lossy, but a flat block should come back within rounding
+        for px in raw.levels[0].chunks(4) {
+            for &c in &px[..3] {
+                assert!((c as i32 - 200).abs() <= 1, "{:?}", px);
+            }
+
+            assert_eq!(px[3], 255);
+        }
+    }
+
+    #[test]
+    fn dds_bc1() {
+        let data = include_bytes!("../../tests/fixtures/bc1.dds");
+
+        // Red and blue endpoints and the two colours between them
+        check(&decode::<Dds>(data, &linear()), &[
+            255, 0, 0,   255,
+            0,   0, 255, 255,
+            170, 0, 85,  255,
+            85,  0, 170, 255,
+        ]);
+    }
+
+    #[test]
+    fn dds_bc3() {
+        let data = include_bytes!("../../tests/fixtures/bc3.dds");
+
+        // Alpha endpoints 255 and 0, then the first and last values between
+        check(&decode::<Dds>(data, &linear()), &[
+            255, 255, 255, 255,
+            255, 255, 255, 0,
+            255, 255, 255, 218,
+            255, 255, 255, 36,
+        ]);
+    }
+
+    #[test]
+    fn dds_unsupported() {
+        let data = include_bytes!("../../tests/fixtures/bc1.dds");
+
+        let mut bc5 = data.to_vec();
+        bc5[84..88].copy_from_slice(b"ATI2");
+
+        assert!(ImageTextureLoader::<Dds>::parse(&bc5).is_err());
+        assert!(ImageTextureLoader::<Dds>::parse(&data[..130]).is_err());
+    }
+
+    #[test]
+    fn wrong_format() {
+        let data = include_bytes!("../../tests/fixtures/rgba.png");
+
+        assert!(ImageTextureLoader::<Bmp>::parse(data).is_err());
+    }
+
+    #[test]
+    fn srgb() {
+        let data = include_bytes!("../../tests/fixtures/rgba.png");
+
+        let options = TextureOptions { srgb: true, ..linear() };
+        let raw     = decode::<Png>(data, &options);
+
+        // Only changes how the texels are sampled, not the texels
+        assert!(raw.srgb);
+        assert!(!decode::<Png>(data, &linear()).srgb);
+        check(&raw, &RGBA);
+    }
+
+    #[test]
+    fn mipmaps() {
+        let data = include_bytes!("../../tests/fixtures/rgba.png");
+
+        let options = TextureOptions { mipmaps: true, ..linear() };
+        let raw     = decode::<Png>(data, &options);
+
+        assert_eq!(raw.levels.len(), 2);
+        assert_eq!(&raw.levels[0][..], &RGBA[..]);
+        assert_eq!(raw.levels[1].len(), 4);
+    }
+
+    #[test]
+    fn premultiply_alpha() {
+        let data = include_bytes!("../../tests/fixtures/rgba.png");
+
+        let options = TextureOptions { premultiply_alpha: true, ..linear() };
 
-        AssetLoader::from_data(assets, tex).expect("Texture load failed").into()
+        check(&decode::<Png>(data, &options), &[
+            255, 0,   0,   255,
+            0,   255, 0,   255,
+            0,   0,   128, 128,
+            0,   0,   0,   0,
+        ]);
     }
 }
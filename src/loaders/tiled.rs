@@ -0,0 +1,1034 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+use amethyst::context::asset_manager::{
+    Asset,
+    Assets,
+    AssetManager,
+    AssetLoader,
+    AssetLoaderRaw,
+    FactoryImpl,
+    Texture,
+};
+use amethyst::ecs::{Component, Entity, VecStorage, World};
+use amethyst::processors::rendering::Renderable;
+use amethyst::processors::transform::{Child, LocalTransform, Transform};
+use amethyst::renderer::VertexPosNormal;
+use base64;
+use yaml_rust::Yaml;
+
+use systems::physics::{BodyDesc, Collider};
+use systems::physics::{ImpulseComponent, PhysicsComponent};
+use super::{xml, yaml};
+use super::{load, load_object, split_file, Dependencies, Parse, UvRect};
+use super::error::{self, LoaderError};
+use super::prefab::spawn_prefab;
+use super::obj::new_mesh;
+use super::xml::Element;
+
+const FLIPPED_HORIZONTALLY: u32 = 0x80000000;
+const FLIPPED_VERTICALLY:   u32 = 0x40000000;
+const FLIPPED_DIAGONALLY:   u32 = 0x20000000;
+const FLIP_FLAGS:           u32 = 0xE0000000;
+
+// Custom properties set in Tiled on the map, a layer or an object
+#[derive(Clone, Debug, PartialEq)]
+pub enum Property {
+    Bool(bool),
+    Int(i64),
+    Float(f32),
+    Str(String),
+}
+
+pub type Properties = HashMap<String, Property>;
+
+impl Property {
+    fn parse(kind: &str, value: &str) -> Result<Self, LoaderError> {
+        let invalid = || LoaderError::parse(
+            None,
+            format!("invalid {} property `{}`", kind, value),
+        );
+
+        match kind {
+            "bool" => match value {
+                "true"  => Ok(Property::Bool(true)),
+                "false" => Ok(Property::Bool(false)),
+                _       => Err(invalid()),
+            },
+            "int"   => value.parse().map(Property::Int).map_err(|_| invalid()),
+            "float" =>
+                value.parse().map(Property::Float).map_err(|_| invalid()),
+            // Strings, colours, files and object references
+            _ => Ok(Property::Str(value.into())),
+        }
+    }
+
+    fn from_yaml(value: &Yaml) -> Option<Self> {
+        match *value {
+            Yaml::Boolean(b)    => Some(Property::Bool(b)),
+            Yaml::Integer(i)    => Some(Property::Int(i)),
+            Yaml::Real(_)       => yaml::number(value).map(Property::Float),
+            Yaml::String(ref s) => Some(Property::Str(s.clone())),
+            _                   => None,
+        }
+    }
+
+    fn to_yaml(&self) -> Yaml {
+        match *self {
+            Property::Bool(b)    => Yaml::Boolean(b),
+            Property::Int(i)     => Yaml::Integer(i),
+            Property::Float(f)   => Yaml::Real(f.to_string()),
+            Property::Str(ref s) => Yaml::String(s.clone()),
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Property::Bool(b) => Some(b),
+            _                 => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Property::Int(i) => Some(i),
+            _                => None,
+        }
+    }
+
+    // Tiled writes whole floats without a decimal point, so ints count too
+    pub fn as_f32(&self) -> Option<f32> {
+        match *self {
+            Property::Int(i)   => Some(i as f32),
+            Property::Float(f) => Some(f),
+            _                  => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Property::Str(ref s) => Some(s),
+            _                    => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    Rectangle,
+    Ellipse,
+    Point,
+    // Relative to the object's position
+    Polygon(Vec<[f32; 2]>),
+}
+
+// An object from one of the map's object layers. Positions and sizes are in
+// tiles; Tiled's y axis points down like ours, so they're otherwise unchanged.
+//
+// Objects are spawned with this as a component so gameplay code can find spawn
+// points, animal types and so on by `kind` and `properties`. A few properties
+// are understood by `TiledMap::spawn` itself, named the same as in prefabs so
+// they mean the same with or without one:
+//
+//     prefab:           prefab to spawn instead, with every other property as
+//                       an override (`physics.friction` sets
+//                       `physics: { friction }`)
+//     model:            object to draw, as given to `loaders::load_object`
+//     collider:         false to spawn without a physics body, unless it's a
+//                       prefab
+//     physics.dynamic:  true for a dynamic body instead of a static one
+//     physics.density, physics.restitution, physics.friction
+//     impulse:          true to give the object an `ImpulseComponent`
+#[derive(Clone, Debug)]
+pub struct MapObject {
+    pub id:         u32,
+    pub name:       String,
+    pub kind:       String,
+    pub layer:      String,
+    pub position:   [f32; 2],
+    pub size:       [f32; 2],
+    // Degrees clockwise. Colliders are always axis-aligned.
+    pub rotation:   f32,
+    pub shape:      Shape,
+    pub properties: Properties,
+}
+
+impl Component for MapObject {
+    type Storage = VecStorage<MapObject>;
+}
+
+impl MapObject {
+    pub fn property(&self, name: &str) -> Option<&Property> {
+        self.properties.get(name)
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.property(name).and_then(Property::as_bool).unwrap_or(false)
+    }
+
+    // Centre and size of the object's bounding box
+    pub fn bounds(&self) -> ([f32; 2], [f32; 2]) {
+        let (min, max) = match self.shape {
+            Shape::Point           => ([0.; 2], [0.; 2]),
+            Shape::Polygon(ref ps) => ps.iter().fold(
+                ([::std::f32::MAX; 2], [::std::f32::MIN; 2]),
+                |(min, max), p| (
+                    [min[0].min(p[0]), min[1].min(p[1])],
+                    [max[0].max(p[0]), max[1].max(p[1])],
+                ),
+            ),
+            _                      => ([0.; 2], self.size),
+        };
+
+        if min[0] > max[0] {
+            return (self.position, [0.; 2]);
+        }
+
+        (
+            [
+                self.position[0] + (min[0] + max[0]) / 2.,
+                self.position[1] + (min[1] + max[1]) / 2.,
+            ],
+            [max[0] - min[0], max[1] - min[1]],
+        )
+    }
+
+    // Polygons get their bounding box, ellipses the circle around them
+    pub fn body(&self) -> Option<BodyDesc> {
+        if self.shape == Shape::Point {
+            return None;
+        }
+
+        let collider = self.property("collider").and_then(Property::as_bool);
+
+        if collider == Some(false) {
+            return None;
+        }
+
+        let (_, size) = self.bounds();
+        let default   = BodyDesc::default();
+
+        let number = |name: &str, default: f32| self.property(name)
+            .and_then(Property::as_f32)
+            .unwrap_or(default);
+
+        Some(BodyDesc {
+            collider:    match self.shape {
+                Shape::Ellipse => Collider::Ball(size[0].max(size[1]) / 2.),
+                _              => Collider::Box(size),
+            },
+            dynamic:     self.flag("physics.dynamic"),
+            density:     number("physics.density", default.density),
+            restitution: number("physics.restitution", default.restitution),
+            friction:    number("physics.friction", default.friction),
+        })
+    }
+
+    // Every property but `prefab` itself, as overrides for the prefab
+    fn overrides(&self) -> Yaml {
+        yaml::from_dotted(
+            self.properties.iter()
+                .filter(|&(key, _)| key != "prefab")
+                .map(|(key, value)| (&key[..], value.to_yaml()))
+        )
+    }
+
+    fn spawn(&self, assets: &mut AssetManager, world: &mut World) -> Entity {
+        let (center, size) = self.bounds();
+
+        if let Some(prefab) = self.property("prefab").and_then(Property::as_str)
+        {
+            let spawned = spawn_prefab(
+                assets,
+                world,
+                prefab,
+                center,
+                &self.overrides(),
+            );
+
+            match spawned {
+                Ok(entity) => {
+                    world.write::<MapObject>().insert(entity, self.clone());
+                    return entity;
+                },
+                Err(e) => {
+                    let _ = writeln!(
+                        io::stderr(),
+                        "Cannot spawn prefab {} for map object `{}`: {}",
+                        prefab,
+                        self.name,
+                        e
+                    );
+                },
+            }
+        }
+
+        let mut l_trans = LocalTransform::default();
+        l_trans.translation = [center[0], center[1], 0.];
+
+        let mut builder = world.create_now()
+            .with(l_trans)
+            .with(Transform::default())
+            .with(self.clone());
+
+        if let Some(body) = self.body() {
+            builder = builder.with(
+                PhysicsComponent::from_desc(body, center)
+            );
+        }
+
+        if self.flag("impulse") {
+            builder = builder.with(ImpulseComponent::default());
+        }
+
+        let entity = builder.build();
+
+        let model = match self.property("model").and_then(Property::as_str) {
+            Some(model) => model,
+            None        => return entity,
+        };
+
+        match load_object(assets, model, "obj") {
+            // Models are unit sized, so they get stretched over the object
+            Ok(renderable) => {
+                let scale = if size == [0.; 2] { [1.; 2] } else { size };
+
+                let mut l_trans = LocalTransform::default();
+                l_trans.translation = [-scale[0] / 2., -scale[1] / 2., 0.];
+                l_trans.scale       = [scale[0], scale[1], 1.];
+
+                world.create_now()
+                    .with(l_trans)
+                    .with(Transform::default())
+                    .with(Child::new(entity))
+                    .with(renderable)
+                    .build();
+            },
+            Err(e) => {
+                let _ = writeln!(
+                    io::stderr(),
+                    "Cannot load {} for map object `{}`: {}",
+                    model,
+                    self.name,
+                    e
+                );
+            },
+        }
+
+        entity
+    }
+}
+
+#[derive(Clone)]
+pub struct TiledMap {
+    pub size:        [u32; 2],
+    pub tile_size:   [f32; 2],
+    pub properties:  Properties,
+    // One batched renderable per tile layer and tileset it uses, bottom layer
+    // first
+    pub renderables: Vec<Renderable>,
+    pub objects:     Vec<MapObject>,
+}
+
+impl TiledMap {
+    pub fn property(&self, name: &str) -> Option<&Property> {
+        self.properties.get(name)
+    }
+
+    pub fn objects_of_kind<'a>(
+        &'a self,
+        kind: &'a str,
+    ) -> impl Iterator<Item = &'a MapObject> + 'a {
+        self.objects.iter().filter(move |o| o.kind == kind)
+    }
+
+    // Smallest and largest corner of the level, which is the tile layers
+    // grown to take in any objects placed outside them
+    pub fn bounds(&self) -> ([f32; 2], [f32; 2]) {
+        let tiles = ([0.; 2], [self.size[0] as f32, self.size[1] as f32]);
+
+        self.objects.iter().map(MapObject::bounds).fold(
+            tiles,
+            |(min, max), (centre, size)| (
+                [
+                    min[0].min(centre[0] - size[0] / 2.),
+                    min[1].min(centre[1] - size[1] / 2.),
+                ],
+                [
+                    max[0].max(centre[0] + size[0] / 2.),
+                    max[1].max(centre[1] + size[1] / 2.),
+                ],
+            ),
+        )
+    }
+
+    // Creates the tile layers and one entity per object, returning all of them
+    // so the caller can tear the level down again.
+    pub fn spawn(
+        &self,
+        assets: &mut AssetManager,
+        world: &mut World,
+    ) -> Vec<Entity> {
+        let mut entities = self.renderables.iter().map(
+            |r| world.create_now()
+                .with(LocalTransform::default())
+                .with(Transform::default())
+                .with(r.clone())
+                .build()
+        ).collect::<Vec<_>>();
+
+        for object in &self.objects {
+            entities.push(object.spawn(assets, world));
+        }
+
+        entities
+    }
+}
+
+// Loads a map if it isn't already, and returns a copy of it
+pub fn load_map(
+    assets: &mut AssetManager,
+    name: &str,
+    ext: &str,
+) -> Result<TiledMap, LoaderError> {
+    let id   = load::<TiledMap>(assets, name, ext)?;
+    let maps = assets.read_assets();
+    let map: Option<&Asset<TiledMap>> = maps.get(id);
+
+    map.map(|m| m.0.clone())
+        .ok_or_else(|| LoaderError::missing(name, ext))
+}
+
+// Loads (if it isn't already) and spawns a map, see `TiledMap::spawn`
+pub fn spawn_map(
+    assets: &mut AssetManager,
+    world: &mut World,
+    name: &str,
+    ext: &str,
+) -> Result<Vec<Entity>, LoaderError> {
+    let map = load_map(assets, name, ext)?;
+
+    Ok(map.spawn(assets, world))
+}
+
+#[derive(Clone, Debug)]
+struct Tileset {
+    first_gid:  u32,
+    // Relative to the asset root, like every other dependency
+    image:      String,
+    image_size: [f32; 2],
+    tile_size:  [f32; 2],
+    columns:    u32,
+    margin:     f32,
+    spacing:    f32,
+}
+
+impl Tileset {
+    fn uv(&self, local: u32) -> UvRect {
+        let columns    = ::std::cmp::max(self.columns, 1);
+        let (col, row) = ((local % columns) as f32, (local / columns) as f32);
+
+        UvRect::from_pixels(
+            [
+                self.margin + col * (self.tile_size[0] + self.spacing),
+                self.margin + row * (self.tile_size[1] + self.spacing),
+                self.tile_size[0],
+                self.tile_size[1],
+            ],
+            self.image_size,
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+struct TileLayer {
+    width: u32,
+    gids:  Vec<u32>,
+}
+
+// Tiled maps, either as .tmx (XML) or .tmj (JSON). Tile data may be CSV or
+// uncompressed base64; tilesets must be embedded in the map rather than kept
+// in .tsx files, and infinite maps aren't supported.
+pub struct TiledLoader {
+    size:       [u32; 2],
+    tile_size:  [f32; 2],
+    properties: Properties,
+    tilesets:   Vec<Tileset>,
+    layers:     Vec<TileLayer>,
+    objects:    Vec<MapObject>,
+}
+
+// Tiled wraps the data in whitespace, which the decoder doesn't skip
+fn decode_base64(data: &str) -> Result<Vec<u8>, LoaderError> {
+    let data = data.split_whitespace().collect::<String>();
+
+    base64::decode(&data).map_err(
+        |e| LoaderError::parse(None, format!("invalid base64: {}", e))
+    )
+}
+
+fn le_u32s(bytes: &[u8]) -> Vec<u32> {
+    bytes.chunks(4).filter(|b| b.len() == 4).map(
+        |b| b[0] as u32 | (b[1] as u32) << 8 |
+            (b[2] as u32) << 16 | (b[3] as u32) << 24
+    ).collect()
+}
+
+fn attr<T: FromStr>(el: &Element, name: &str) -> Result<T, LoaderError> {
+    let value = el.attr(name).ok_or_else(|| LoaderError::parse(
+        None,
+        format!("<{}> is missing `{}`", el.name, name),
+    ))?;
+
+    value.parse().map_err(|_| LoaderError::parse(
+        None,
+        format!("invalid `{}` on <{}>", name, el.name),
+    ))
+}
+
+fn attr_or<T: FromStr>(
+    el: &Element,
+    name: &str,
+    default: T,
+) -> Result<T, LoaderError> {
+    match el.attr(name) {
+        Some(_) => attr(el, name),
+        None    => Ok(default),
+    }
+}
+
+fn tmx_properties(el: &Element) -> Result<Properties, LoaderError> {
+    let props = match el.child("properties") {
+        Some(props) => props,
+        None        => return Ok(HashMap::new()),
+    };
+
+    props.children_named("property").map(|p| {
+        let name  = attr::<String>(p, "name")?;
+        // Multi-line strings are stored as text instead of an attribute
+        let value = p.attr("value").unwrap_or(&p.text);
+        let kind  = p.attr("type").unwrap_or("string");
+
+        Ok((name, Property::parse(kind, value)?))
+    }).collect()
+}
+
+fn tmx_tileset(el: &Element) -> Result<Tileset, LoaderError> {
+    if el.attr("source").is_some() {
+        return Err(LoaderError::UnsupportedFormat(
+            "external tilesets (.tsx)".into()
+        ));
+    }
+
+    let image = el.child("image").ok_or(
+        LoaderError::parse(None, "tileset without an image")
+    )?;
+
+    Ok(Tileset {
+        first_gid:  attr(el, "firstgid")?,
+        image:      attr(image, "source")?,
+        image_size: [attr(image, "width")?, attr(image, "height")?],
+        tile_size:  [attr(el, "tilewidth")?, attr(el, "tileheight")?],
+        columns:    attr_or(el, "columns", 1)?,
+        margin:     attr_or(el, "margin", 0.)?,
+        spacing:    attr_or(el, "spacing", 0.)?,
+    })
+}
+
+fn tmx_tiles(layer: &Element) -> Result<Vec<u32>, LoaderError> {
+    let data = layer.child("data").ok_or(
+        LoaderError::parse(None, "<layer> without <data>")
+    )?;
+
+    if data.child("chunk").is_some() {
+        return Err(LoaderError::UnsupportedFormat("infinite maps".into()));
+    }
+
+    if let Some(compression) = data.attr("compression") {
+        return Err(LoaderError::UnsupportedFormat(
+            format!("{} compressed tile data", compression)
+        ));
+    }
+
+    match data.attr("encoding") {
+        Some("csv") => data.text.split(',')
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(|t| t.parse().map_err(
+                |_| LoaderError::parse(None, "invalid tile in CSV data")
+            ))
+            .collect(),
+        Some("base64") => Ok(le_u32s(&decode_base64(&data.text)?)),
+        Some(other) => Err(LoaderError::UnsupportedFormat(
+            format!("{} tile data", other)
+        )),
+        None => data.children_named("tile")
+            .map(|t| attr_or(t, "gid", 0))
+            .collect(),
+    }
+}
+
+fn tmx_object(
+    obj: &Element,
+    layer: &str,
+    tile_size: [f32; 2],
+) -> Result<MapObject, LoaderError> {
+    let poly = obj.child("polygon").or(obj.child("polyline"));
+
+    let shape = if obj.child("ellipse").is_some() {
+        Shape::Ellipse
+    } else if obj.child("point").is_some() {
+        Shape::Point
+    } else if let Some(poly) = poly {
+        let points = attr::<String>(poly, "points")?;
+        let points = points.split_whitespace().map(|p| {
+            let mut xy = p.split(',').map(|c| c.parse::<f32>().ok());
+
+            match (xy.next(), xy.next()) {
+                (Some(Some(x)), Some(Some(y))) =>
+                    Ok([x / tile_size[0], y / tile_size[1]]),
+                _ => Err(LoaderError::parse(None, "invalid polygon point")),
+            }
+        }).collect::<Result<Vec<_>, _>>()?;
+
+        Shape::Polygon(points)
+    } else {
+        Shape::Rectangle
+    };
+
+    let size = [
+        attr_or(obj, "width", 0.)? / tile_size[0],
+        attr_or(obj, "height", 0.)? / tile_size[1],
+    ];
+
+    let mut position = [
+        attr::<f32>(obj, "x")? / tile_size[0],
+        attr::<f32>(obj, "y")? / tile_size[1],
+    ];
+
+    // Tile objects are positioned by their bottom-left corner
+    if obj.attr("gid").is_some() {
+        position[1] -= size[1];
+    }
+
+    Ok(MapObject {
+        id:         attr_or(obj, "id", 0)?,
+        name:       attr_or(obj, "name", String::new())?,
+        kind:       obj.attr("class").or(obj.attr("type")).unwrap_or("").into(),
+        layer:      layer.into(),
+        position:   position,
+        size:       size,
+        rotation:   attr_or(obj, "rotation", 0.)?,
+        shape:      shape,
+        properties: tmx_properties(obj)?,
+    })
+}
+
+fn tmx_layers(
+    el: &Element,
+    tile_size: [f32; 2],
+    layers: &mut Vec<TileLayer>,
+    objects: &mut Vec<MapObject>,
+) -> Result<(), LoaderError> {
+    for child in &el.children {
+        match &child.name[..] {
+            "layer" => if attr_or(child, "visible", 1)? != 0 {
+                layers.push(TileLayer {
+                    width: attr(child, "width")?,
+                    gids:  tmx_tiles(child)?,
+                });
+            },
+            "objectgroup" => {
+                let layer = attr_or(child, "name", String::new())?;
+
+                for obj in child.children_named("object") {
+                    objects.push(tmx_object(obj, &layer, tile_size)?);
+                }
+            },
+            "group" => tmx_layers(child, tile_size, layers, objects)?,
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_tmx(data: &[u8]) -> Result<TiledLoader, LoaderError> {
+    let map = xml::parse(data)?;
+
+    if map.name != "map" {
+        return Err(LoaderError::parse(None, "expected a <map> element"));
+    }
+
+    if map.attr("infinite") == Some("1") {
+        return Err(LoaderError::UnsupportedFormat("infinite maps".into()));
+    }
+
+    let tile_size = [attr(&map, "tilewidth")?, attr(&map, "tileheight")?];
+
+    let mut layers  = vec![];
+    let mut objects = vec![];
+    tmx_layers(&map, tile_size, &mut layers, &mut objects)?;
+
+    Ok(TiledLoader {
+        size:       [attr(&map, "width")?, attr(&map, "height")?],
+        tile_size:  tile_size,
+        properties: tmx_properties(&map)?,
+        tilesets:   map.children_named("tileset")
+            .map(tmx_tileset)
+            .collect::<Result<_, _>>()?,
+        layers:     layers,
+        objects:    objects,
+    })
+}
+
+fn uint(yaml: &Yaml) -> Option<u32> {
+    yaml.as_i64().map(|i| i as u32)
+}
+
+fn string(yaml: &Yaml, key: &str) -> String {
+    yaml[key].as_str().unwrap_or("").into()
+}
+
+fn json_properties(yaml: &Yaml) -> Result<Properties, LoaderError> {
+    let props = match yaml["properties"].as_vec() {
+        Some(props) => props,
+        None        => return Ok(HashMap::new()),
+    };
+
+    props.iter().map(|p| {
+        let name  = yaml::require(p, "name", Yaml::as_str)?;
+        let value = yaml::require(p, "value", Property::from_yaml)?;
+
+        Ok((name.to_string(), value))
+    }).collect()
+}
+
+fn json_tileset(yaml: &Yaml) -> Result<Tileset, LoaderError> {
+    if yaml["source"].as_str().is_some() {
+        return Err(LoaderError::UnsupportedFormat(
+            "external tilesets (.tsj)".into()
+        ));
+    }
+
+    Ok(Tileset {
+        first_gid:  yaml::require(yaml, "firstgid", uint)?,
+        image:      yaml::require(yaml, "image", Yaml::as_str)?.into(),
+        image_size: [
+            yaml::require(yaml, "imagewidth", yaml::number)?,
+            yaml::require(yaml, "imageheight", yaml::number)?,
+        ],
+        tile_size:  [
+            yaml::require(yaml, "tilewidth", yaml::number)?,
+            yaml::require(yaml, "tileheight", yaml::number)?,
+        ],
+        columns:    uint(&yaml["columns"]).unwrap_or(1),
+        margin:     yaml::number(&yaml["margin"]).unwrap_or(0.),
+        spacing:    yaml::number(&yaml["spacing"]).unwrap_or(0.),
+    })
+}
+
+fn json_tiles(layer: &Yaml) -> Result<Vec<u32>, LoaderError> {
+    if layer["chunks"].as_vec().is_some() {
+        return Err(LoaderError::UnsupportedFormat("infinite maps".into()));
+    }
+
+    match layer["compression"].as_str() {
+        None | Some("") => (),
+        Some(compression) => return Err(LoaderError::UnsupportedFormat(
+            format!("{} compressed tile data", compression)
+        )),
+    }
+
+    match layer["data"] {
+        Yaml::Array(ref tiles) => tiles.iter().map(uint)
+            .collect::<Option<_>>()
+            .ok_or(LoaderError::parse(None, "invalid tile data")),
+        Yaml::String(ref data) => Ok(le_u32s(&decode_base64(data)?)),
+        _ => Err(LoaderError::parse(None, "tile layer without data")),
+    }
+}
+
+fn json_object(
+    yaml: &Yaml,
+    layer: &str,
+    tile_size: [f32; 2],
+) -> Result<MapObject, LoaderError> {
+    let poly = yaml["polygon"].as_vec().or(yaml["polyline"].as_vec());
+
+    let shape = if yaml["ellipse"].as_bool() == Some(true) {
+        Shape::Ellipse
+    } else if yaml["point"].as_bool() == Some(true) {
+        Shape::Point
+    } else if let Some(poly) = poly {
+        Shape::Polygon(poly.iter().map(|p| Ok([
+            yaml::require(p, "x", yaml::number)? / tile_size[0],
+            yaml::require(p, "y", yaml::number)? / tile_size[1],
+        ])).collect::<Result<_, LoaderError>>()?)
+    } else {
+        Shape::Rectangle
+    };
+
+    let size = [
+        yaml::number(&yaml["width"]).unwrap_or(0.) / tile_size[0],
+        yaml::number(&yaml["height"]).unwrap_or(0.) / tile_size[1],
+    ];
+
+    let mut position = [
+        yaml::require(yaml, "x", yaml::number)? / tile_size[0],
+        yaml::require(yaml, "y", yaml::number)? / tile_size[1],
+    ];
+
+    // Tile objects are positioned by their bottom-left corner
+    if yaml["gid"].as_i64().is_some() {
+        position[1] -= size[1];
+    }
+
+    let kind = yaml["class"].as_str().or(yaml["type"].as_str()).unwrap_or("");
+
+    Ok(MapObject {
+        id:         uint(&yaml["id"]).unwrap_or(0),
+        name:       string(yaml, "name"),
+        kind:       kind.into(),
+        layer:      layer.into(),
+        position:   position,
+        size:       size,
+        rotation:   yaml::number(&yaml["rotation"]).unwrap_or(0.),
+        shape:      shape,
+        properties: json_properties(yaml)?,
+    })
+}
+
+fn json_layers(
+    yaml: &Yaml,
+    tile_size: [f32; 2],
+    layers: &mut Vec<TileLayer>,
+    objects: &mut Vec<MapObject>,
+) -> Result<(), LoaderError> {
+    let empty = vec![];
+
+    for layer in yaml.as_vec().unwrap_or(&empty) {
+        match layer["type"].as_str() {
+            Some("tilelayer") => if layer["visible"].as_bool() != Some(false) {
+                layers.push(TileLayer {
+                    width: yaml::require(layer, "width", uint)?,
+                    gids:  json_tiles(layer)?,
+                });
+            },
+            Some("objectgroup") => {
+                let name = string(layer, "name");
+
+                for obj in layer["objects"].as_vec().unwrap_or(&empty) {
+                    objects.push(json_object(obj, &name, tile_size)?);
+                }
+            },
+            Some("group") =>
+                json_layers(&layer["layers"], tile_size, layers, objects)?,
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_json(data: &[u8]) -> Result<TiledLoader, LoaderError> {
+    let doc = yaml::parse(data)?;
+
+    if doc["infinite"].as_bool() == Some(true) {
+        return Err(LoaderError::UnsupportedFormat("infinite maps".into()));
+    }
+
+    let tile_size = [
+        yaml::require(&doc, "tilewidth", yaml::number)?,
+        yaml::require(&doc, "tileheight", yaml::number)?,
+    ];
+
+    let mut layers  = vec![];
+    let mut objects = vec![];
+    json_layers(&doc["layers"], tile_size, &mut layers, &mut objects)?;
+
+    let empty = vec![];
+
+    Ok(TiledLoader {
+        size:       [
+            yaml::require(&doc, "width", uint)?,
+            yaml::require(&doc, "height", uint)?,
+        ],
+        tile_size:  tile_size,
+        properties: json_properties(&doc)?,
+        tilesets:   doc["tilesets"].as_vec().unwrap_or(&empty).iter()
+            .map(json_tileset)
+            .collect::<Result<_, _>>()?,
+        layers:     layers,
+        objects:    objects,
+    })
+}
+
+impl Parse for TiledLoader {
+    fn parse(data: &[u8]) -> Result<Self, LoaderError> {
+        let json = data.iter()
+            .find(|b| !(**b as char).is_whitespace())
+            .map_or(false, |&b| b == b'{');
+
+        if json { parse_json(data) } else { parse_tmx(data) }
+    }
+}
+
+impl Dependencies for TiledLoader {
+    fn dependencies(&self) -> Vec<String> {
+        let mut deps = self.tilesets.iter()
+            .map(|t| t.image.clone())
+            .collect::<Vec<_>>();
+
+        for object in &self.objects {
+            let model  = object.property("model").and_then(Property::as_str);
+            let prefab = object.property("prefab").and_then(Property::as_str);
+
+            let files = model
+                .map(|m| format!("{}.obj", m.splitn(2, '#').next().unwrap()))
+                .into_iter()
+                .chain(prefab.map(|p| format!("{}.prefab", p)));
+
+            for file in files {
+                if !deps.contains(&file) {
+                    deps.push(file);
+                }
+            }
+        }
+
+        deps
+    }
+}
+
+impl AssetLoaderRaw for TiledLoader {
+    fn from_raw(_assets: &Assets, data: &[u8]) -> Option<Self> {
+        match Self::parse(data) {
+            Ok(loader) => Some(loader),
+            Err(e)     => error::report("tmx", e),
+        }
+    }
+}
+
+fn flip(corner: [f32; 2], gid: u32) -> [f32; 2] {
+    let (mut u, mut v) = (corner[0], corner[1]);
+
+    if gid & FLIPPED_DIAGONALLY != 0 {
+        ::std::mem::swap(&mut u, &mut v);
+    }
+    if gid & FLIPPED_HORIZONTALLY != 0 {
+        u = 1. - u;
+    }
+    if gid & FLIPPED_VERTICALLY != 0 {
+        v = 1. - v;
+    }
+
+    [u, v]
+}
+
+// Every tile becomes a unit quad, grouped by the tileset it comes from so each
+// group can be drawn with a single texture.
+fn layer_quads(
+    layer: &TileLayer,
+    tilesets: &[Tileset],
+) -> Vec<(Vec<VertexPosNormal>, Vec<u32>)> {
+    let mut batches = vec![(vec![], vec![]); tilesets.len()];
+    let width       = ::std::cmp::max(layer.width, 1) as usize;
+
+    for (i, &raw) in layer.gids.iter().enumerate() {
+        let gid = raw & !FLIP_FLAGS;
+
+        if gid == 0 {
+            continue;
+        }
+
+        let t = match tilesets.iter().rposition(|t| t.first_gid <= gid) {
+            Some(t) => t,
+            None    => continue,
+        };
+
+        let rect       = tilesets[t].uv(gid - tilesets[t].first_gid);
+        let (col, row) = ((i % width) as f32, (i / width) as f32);
+
+        let (ref mut verts, ref mut indices) = batches[t];
+        let base = verts.len() as u32;
+
+        for corner in &[[0., 1.], [0., 0.], [1., 0.], [1., 1.]] {
+            verts.push(VertexPosNormal {
+                pos:       [col + corner[0], row + corner[1], 0.],
+                normal:    [0., 0., 1.],
+                tex_coord: rect.map(flip(*corner, raw)),
+            });
+        }
+
+        indices.extend([0, 1, 2, 0, 2, 3].iter().map(|i| base + i));
+    }
+
+    batches
+}
+
+impl AssetLoader<TiledMap> for TiledLoader {
+    fn from_data(_assets: &mut Assets, _data: Self) -> Option<TiledMap> {
+        error::report(
+            "tmx",
+            LoaderError::UnsupportedFormat(
+                "maps must be loaded through the asset manager".into()
+            ),
+        )
+    }
+
+    fn load_from_data(
+        assets: &mut AssetManager,
+        data: Self,
+    ) -> Option<TiledMap> {
+        // Mesh names have to be unique across every map ever loaded
+        static MAPS: AtomicUsize = ATOMIC_USIZE_INIT;
+        let map_id = MAPS.fetch_add(1, Ordering::Relaxed);
+
+        let mut renderables = vec![];
+
+        for (l, layer) in data.layers.iter().enumerate() {
+            let batches = layer_quads(layer, &data.tilesets);
+
+            for (t, (verts, indices)) in batches.into_iter().enumerate() {
+                if verts.is_empty() {
+                    continue;
+                }
+
+                let (texture, ext) = split_file(&data.tilesets[t].image, "png");
+
+                let loaded = assets.id_from_name(texture)
+                    .map(|id| assets.read_assets::<Texture>().get(id).is_some())
+                    .unwrap_or(false);
+
+                if !loaded {
+                    return error::report(
+                        "tmx",
+                        LoaderError::missing(texture, ext),
+                    );
+                }
+
+                let name = format!("map{}#{}#{}", map_id, l, t);
+                let mesh = assets.get_loader_mut::<FactoryImpl>()
+                    .ok_or(LoaderError::Upload("no factory".into()))
+                    .and_then(|f| new_mesh(f, &verts, &indices));
+
+                match mesh {
+                    Ok(mesh) => { assets.add_asset(&name, mesh); },
+                    Err(e)   => return error::report("tmx", e),
+                }
+
+                renderables.push(Renderable::new(&name, texture, texture));
+            }
+        }
+
+        TiledMap {
+            size:        data.size,
+            tile_size:   data.tile_size,
+            properties:  data.properties,
+            renderables: renderables,
+            objects:     data.objects,
+        }.into()
+    }
+}
@@ -0,0 +1,284 @@
+use amethyst::renderer::VertexPosNormal;
+
+// How normals are generated for models that don't have any. Registered with
+// `AssetManager::add_loader` like `TextureOptions`, and smooth if it isn't.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NormalMode {
+    // Average the normals of every face sharing a vertex
+    Smooth,
+    // Split vertices so that each face gets its own normal
+    Flat,
+}
+
+impl Default for NormalMode {
+    fn default() -> Self {
+        NormalMode::Smooth
+    }
+}
+
+// Builds a vertex buffer from flattened attribute arrays. Any missing (or
+// short) normal or texcoord data is generated rather than truncating the whole
+// mesh.
+pub fn assemble(
+    positions: &[f32],
+    normals: &[f32],
+    texcoords: &[f32],
+    indices: &[u32],
+    mode: NormalMode,
+) -> (Vec<VertexPosNormal>, Vec<u32>) {
+    let count     = positions.len() / 3;
+    let has_norms = normals.len() >= count * 3;
+    let has_uvs   = texcoords.len() >= count * 2;
+
+    let uvs = if has_uvs {
+        texcoords[..count * 2].to_vec()
+    } else {
+        planar_uvs(positions)
+    };
+
+    if !has_norms && mode == NormalMode::Flat {
+        return flat(positions, &uvs, indices);
+    }
+
+    let norms = if has_norms {
+        normals[..count * 3].to_vec()
+    } else {
+        smooth_normals(positions, indices)
+    };
+
+    let verts = positions.chunks(3)
+        .zip(norms.chunks(3))
+        .zip(uvs.chunks(2))
+        .map(
+            |((pos, norm), uv)|
+            VertexPosNormal {
+                pos: [pos[0], pos[1], pos[2]],
+                normal: [norm[0], norm[1], norm[2]],
+                tex_coord: [uv[0], uv[1]],
+            }
+        )
+        .collect();
+
+    (verts, indices.to_vec())
+}
+
+fn position(positions: &[f32], i: u32) -> [f32; 3] {
+    let i = i as usize * 3;
+
+    [positions[i], positions[i + 1], positions[i + 2]]
+}
+
+fn face_normal(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> [f32; 3] {
+    let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+
+    [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ]
+}
+
+fn normalize(n: [f32; 3]) -> [f32; 3] {
+    let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+
+    if len > ::std::f32::EPSILON {
+        [n[0] / len, n[1] / len, n[2] / len]
+    } else {
+        [0., 0., 1.]
+    }
+}
+
+// Face normals aren't normalized before accumulating, so larger faces get a
+// proportionally larger say in the result.
+fn smooth_normals(positions: &[f32], indices: &[u32]) -> Vec<f32> {
+    let mut out = vec![0.; positions.len()];
+
+    for tri in indices.chunks(3).filter(|t| t.len() == 3) {
+        let n = face_normal(
+            position(positions, tri[0]),
+            position(positions, tri[1]),
+            position(positions, tri[2]),
+        );
+
+        for &i in tri {
+            let i = i as usize * 3;
+
+            out[i]     += n[0];
+            out[i + 1] += n[1];
+            out[i + 2] += n[2];
+        }
+    }
+
+    out.chunks(3)
+        .flat_map(|n| normalize([n[0], n[1], n[2]]).to_vec())
+        .collect()
+}
+
+fn flat(
+    positions: &[f32],
+    uvs: &[f32],
+    indices: &[u32],
+) -> (Vec<VertexPosNormal>, Vec<u32>) {
+    let mut verts = Vec::with_capacity(indices.len());
+
+    for tri in indices.chunks(3).filter(|t| t.len() == 3) {
+        let n = normalize(face_normal(
+            position(positions, tri[0]),
+            position(positions, tri[1]),
+            position(positions, tri[2]),
+        ));
+
+        for &i in tri {
+            let uv = i as usize * 2;
+
+            verts.push(VertexPosNormal {
+                pos: position(positions, i),
+                normal: n,
+                tex_coord: [uvs[uv], uvs[uv + 1]],
+            });
+        }
+    }
+
+    let indices = (0..verts.len() as u32).collect();
+
+    (verts, indices)
+}
+
+// Projects onto the XY plane and stretches over the model's bounding box, which
+// is exactly right for the flat quads this game is built from.
+fn planar_uvs(positions: &[f32]) -> Vec<f32> {
+    use std::f32::{INFINITY, NEG_INFINITY};
+
+    let (min, max) = positions.chunks(3).fold(
+        ([INFINITY; 2], [NEG_INFINITY; 2]),
+        |(min, max), p| (
+            [min[0].min(p[0]), min[1].min(p[1])],
+            [max[0].max(p[0]), max[1].max(p[1])],
+        )
+    );
+
+    let extent = |i: usize| {
+        let e = max[i] - min[i];
+        if e > ::std::f32::EPSILON { e } else { 1. }
+    };
+
+    positions.chunks(3)
+        .flat_map(|p| vec![
+            (p[0] - min[0]) / extent(0),
+            (p[1] - min[1]) / extent(1),
+        ])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tobj::{parse_obj, Model};
+
+    use super::*;
+
+    const QUAD: &'static str = include_str!("../../resources/assets/quad.obj");
+
+    fn object(name: &str) -> Model {
+        let (models, _) = parse_obj(&mut QUAD.as_bytes())
+            .unwrap_or_else(|e| panic!("quad.obj: {:?}", e));
+
+        models.into_iter()
+            .find(|m| m.name == name)
+            .unwrap_or_else(|| panic!("quad.obj has no {}", name))
+    }
+
+    fn build(name: &str, mode: NormalMode) -> (Vec<VertexPosNormal>, Vec<u32>) {
+        let model = object(name);
+
+        assemble(
+            &model.mesh.positions,
+            &model.mesh.normals,
+            &model.mesh.texcoords,
+            &model.mesh.indices,
+            mode,
+        )
+    }
+
+    // Every face in quad.obj is in the XY plane, facing +Z, and its texture
+    // coordinates are the same as its XY position, which is also what planar
+    // UVs come out as
+    fn check(verts: &[VertexPosNormal]) {
+        for v in verts {
+            assert_eq!(v.normal, [0., 0., 1.]);
+            assert_eq!(v.tex_coord, [v.pos[0], v.pos[1]]);
+        }
+    }
+
+    #[test]
+    fn positions_only() {
+        let (verts, indices) = build("Tri_v", NormalMode::Smooth);
+
+        assert_eq!(verts.len(), 3);
+        assert_eq!(indices.len(), 3);
+        check(&verts);
+    }
+
+    #[test]
+    fn texcoords() {
+        let (verts, indices) = build("Quad", NormalMode::Smooth);
+
+        assert_eq!(verts.len(), 4);
+        assert_eq!(indices.len(), 6);
+        check(&verts);
+    }
+
+    #[test]
+    fn normals() {
+        let (verts, indices) = build("Tri_v_vn", NormalMode::Smooth);
+
+        assert_eq!(verts.len(), 3);
+        assert_eq!(indices.len(), 3);
+        check(&verts);
+    }
+
+    #[test]
+    fn texcoords_and_normals() {
+        // A quad face, split into two triangles
+        let (verts, indices) = build("Quad_face", NormalMode::Smooth);
+
+        assert_eq!(verts.len(), 4);
+        assert_eq!(indices.len(), 6);
+        check(&verts);
+    }
+
+    #[test]
+    fn flat() {
+        let (verts, indices) = build("Quad", NormalMode::Flat);
+
+        // A vertex per corner of each triangle
+        assert_eq!(verts.len(), 6);
+        assert_eq!(indices, (0..6).collect::<Vec<_>>());
+        check(&verts);
+    }
+
+    #[test]
+    fn flat_keeps_normals() {
+        let (verts, indices) = build("Tri_v_vn", NormalMode::Flat);
+
+        assert_eq!(verts.len(), 3);
+        assert_eq!(indices.len(), 3);
+        check(&verts);
+    }
+
+    #[test]
+    fn short_normals_are_generated() {
+        let model = object("Quad");
+
+        let (verts, _) = assemble(
+            &model.mesh.positions,
+            &[0., 0.],
+            &model.mesh.texcoords,
+            &model.mesh.indices,
+            NormalMode::Smooth,
+        );
+
+        assert_eq!(verts.len(), 4);
+        check(&verts);
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+
+use xml::reader::{EventReader, XmlEvent};
+
+use super::error::LoaderError;
+
+// A tree of what xml-rs reads, which is easier to pick formats apart with than
+// its events: elements, attributes, text and CDATA. Namespace prefixes are
+// dropped from names, none of the formats we load use them.
+#[derive(Clone, Debug, Default)]
+pub struct Element {
+    pub name:     String,
+    pub attrs:    HashMap<String, String>,
+    pub children: Vec<Element>,
+    pub text:     String,
+}
+
+impl Element {
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs.get(name).map(|a| &a[..])
+    }
+
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    pub fn children_named<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+}
+
+pub fn parse(data: &[u8]) -> Result<Element, LoaderError> {
+    let mut open = Vec::<Element>::new();
+    let mut root = None;
+
+    for event in EventReader::new(data) {
+        match event.map_err(LoaderError::from_xml)? {
+            // xml-rs allows more than one
+            XmlEvent::StartElement { .. }
+                if open.is_empty() && root.is_some() => return Err(
+                    LoaderError::parse(None, "content after the root element")
+                ),
+            XmlEvent::StartElement { name, attributes, .. } =>
+                open.push(Element {
+                    name:  name.local_name,
+                    attrs: attributes.into_iter()
+                        .map(|a| (a.name.local_name, a.value))
+                        .collect(),
+                    ..Element::default()
+                }),
+            // xml-rs has already checked it matches the last start
+            XmlEvent::EndElement { .. } => {
+                let element = open.pop().unwrap();
+
+                match open.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None         => root = Some(element),
+                }
+            },
+            XmlEvent::Characters(text) |
+            XmlEvent::CData(text) |
+            XmlEvent::Whitespace(text) => {
+                if let Some(element) = open.last_mut() {
+                    element.text.push_str(&text);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    root.ok_or(LoaderError::parse(None, "no root element"))
+}
@@ -0,0 +1,145 @@
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use yaml_rust::{Yaml, YamlEmitter, YamlLoader};
+use yaml_rust::yaml::Hash;
+
+use super::error::LoaderError;
+
+pub fn parse(data: &[u8]) -> Result<Yaml, LoaderError> {
+    let text = ::std::str::from_utf8(data)
+        .map_err(|e| LoaderError::parse(None, format!("{}", e)))?;
+
+    let mut docs = YamlLoader::load_from_str(text).map_err(
+        |e| LoaderError::parse(Some(e.marker().line() + 1), format!("{}", e))
+    )?;
+
+    if docs.is_empty() {
+        Err(LoaderError::parse(None, "empty document"))
+    } else {
+        Ok(docs.swap_remove(0))
+    }
+}
+
+// For files outside the asset store, like config.yml
+pub fn read_file(path: &Path) -> io::Result<Yaml> {
+    let mut data = vec![];
+    File::open(path)?.read_to_end(&mut data)?;
+
+    parse(&data).map_err(
+        |e| io::Error::new(io::ErrorKind::InvalidData, format!("{}", e))
+    )
+}
+
+pub fn write_file(path: &Path, yaml: &Yaml) -> io::Result<()> {
+    let mut out = String::new();
+
+    YamlEmitter::new(&mut out).dump(yaml).map_err(
+        |e| io::Error::new(io::ErrorKind::Other, format!("{:?}", e))
+    )?;
+    out.push('\n');
+
+    File::create(path)?.write_all(out.as_bytes())
+}
+
+pub fn number(yaml: &Yaml) -> Option<f32> {
+    match *yaml {
+        Yaml::Integer(i) => Some(i as f32),
+        Yaml::Real(_)    => yaml.as_f64().map(|f| f as f32),
+        _                => None,
+    }
+}
+
+pub fn numbers(yaml: &Yaml) -> Option<Vec<f32>> {
+    yaml.as_vec().and_then(|v| v.iter().map(number).collect())
+}
+
+pub fn pair(yaml: &Yaml) -> Option<[f32; 2]> {
+    numbers(yaml).and_then(
+        |v| if v.len() == 2 { Some([v[0], v[1]]) } else { None }
+    )
+}
+
+pub fn triple(yaml: &Yaml) -> Option<[f32; 3]> {
+    numbers(yaml).and_then(
+        |v| if v.len() == 3 { Some([v[0], v[1], v[2]]) } else { None }
+    )
+}
+
+pub fn require<'a, T, F>(
+    yaml: &'a Yaml,
+    key: &str,
+    convert: F,
+) -> Result<T, LoaderError>
+    where F: FnOnce(&'a Yaml) -> Option<T>
+{
+    match yaml[key] {
+        Yaml::BadValue => Err(
+            LoaderError::parse(None, format!("missing key `{}`", key))
+        ),
+        ref value => convert(value).ok_or_else(
+            || LoaderError::parse(None, format!("invalid value for `{}`", key))
+        ),
+    }
+}
+
+// Recursively merges `over` on top of `base`: hashes are merged key by key,
+// anything else in `over` replaces what was there.
+pub fn merge(base: &Yaml, over: &Yaml) -> Yaml {
+    match (base, over) {
+        (&Yaml::Hash(ref base), &Yaml::Hash(ref over)) => {
+            let mut out = base.clone();
+
+            for (key, value) in over {
+                let merged = match out.get(key) {
+                    Some(base) => merge(base, value),
+                    None       => value.clone(),
+                };
+
+                out.insert(key.clone(), merged);
+            }
+
+            Yaml::Hash(out)
+        },
+        (_, &Yaml::Null) | (_, &Yaml::BadValue) => base.clone(),
+        _ => over.clone(),
+    }
+}
+
+// Builds nested hashes out of dotted keys, so `physics.friction: 0.9` becomes
+// `physics: { friction: 0.9 }`.
+pub fn from_dotted<'a, I>(pairs: I) -> Yaml
+    where I: IntoIterator<Item = (&'a str, Yaml)>
+{
+    let mut out = Yaml::Hash(Hash::new());
+
+    for (key, value) in pairs {
+        let nested = key.rsplit('.').fold(value, |value, part| {
+            let mut hash = Hash::new();
+            hash.insert(Yaml::String(part.into()), value);
+
+            Yaml::Hash(hash)
+        });
+
+        out = merge(&out, &nested);
+    }
+
+    out
+}
+
+pub fn real(f: f32) -> Yaml {
+    Yaml::Real(f.to_string())
+}
+
+pub fn reals(fs: &[f32]) -> Yaml {
+    Yaml::Array(fs.iter().cloned().map(real).collect())
+}
+
+pub fn hash(pairs: Vec<(&str, Yaml)>) -> Yaml {
+    Yaml::Hash(
+        pairs.into_iter()
+            .map(|(key, value)| (Yaml::String(key.into()), value))
+            .collect()
+    )
+}
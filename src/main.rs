@@ -1,5 +1,7 @@
 #![feature(conservative_impl_trait)]
 
+extern crate base64;
+#[macro_use]
 extern crate gfx;
 extern crate gfx_device_gl;
 extern crate image;
@@ -8,39 +10,56 @@ extern crate nalgebra;
 extern crate ncollide;
 extern crate nphysics2d;
 extern crate tobj;
-
+extern crate xml;
+extern crate yaml_rust;
+
+use std::cell::Cell;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use amethyst::context::{
     Context,
     ContextConfig,
 };
-use amethyst::context::asset_manager::{
-    Texture,
-    DirectoryStore,
-};
-use amethyst::engine::{
-    Application,
-    State,
-    Trans,
-};
+use amethyst::context::asset_manager::{FactoryImpl, Texture};
+use amethyst::engine::Application;
 use amethyst::processors::rendering::*;
 use amethyst::processors::transform::*;
 use amethyst::ecs::{
-    World,
     Join,
     RunArg,
     Processor,
 };
-use ncollide::shape::Cuboid;
 use nphysics2d::math::Vector;
-use nphysics2d::object::RigidBody;
 
+mod cli;
 mod loaders;
+mod replay;
+mod rng;
+mod scene;
+mod settings;
+mod states;
+mod stores;
 mod systems;
 
+use systems::animation::*;
+use systems::brightness::{self, Brightness, BrightnessProcessor};
+use systems::camera::*;
+use systems::input::*;
 use systems::physics::*;
+use systems::reload::*;
+use systems::viewport::*;
+use cli::Options;
 use loaders::*;
+use replay::Replay;
+use rng::Rng;
+use settings::{DisplaySettings, Restart};
+use states::{HuntState, Level, LoadingState, MenuState};
+use stores::AssetPaths;
 
 struct ImpulseProcessor;
 
@@ -105,192 +124,243 @@ impl Processor<Arc<Mutex<Context>>> for ImpulseProcessor {
     }
 }
 
-struct HelloWorld;
-
-impl State for HelloWorld {
-    fn on_start(&mut self, ctx: &mut Context, world: &mut World) {
-        use amethyst::context::asset_manager::Asset;
-
-        let (w, h) = ctx.renderer.get_dimensions().unwrap();
-        let aspect = w as f32 / h as f32;
-        let eye    = [0., 0., 0.1];
-        let target = [0., 0., 0.];
-        let up     = [0., -1., 0.];
-
-        // Get an Orthographic projection
-        let projection = Projection::Orthographic {
-            left:    1.0 * aspect,
-            right:  -1.0 * aspect,
-            bottom: -1.0,
-            top:     1.0,
-            near:    0.0,
-            far:     1.0,
-        };
-
-        world.add_resource(projection);
-
-        // Create a camera entity
-        let mut camera = Camera::new(projection, eye, target, up);
-        camera.activate();
-        world.create_now()
-            .with(camera)
-            .build();
-
-        ctx.asset_manager.load_asset::<Texture>("default", "png");
-        let quad = ctx.asset_manager.load_asset::<Vec<Renderable>>(
-            "quad",
-            "obj",
-        ).expect("Cannot load quad");
-        let square = {
-            let assets = ctx.asset_manager.read_assets();
-            let renderables: &Asset<Vec<Renderable>> = assets
-                .get(quad)
-                .expect("Cannot get quad");
-
-            renderables.0[1].clone()
-        };
+// Hunted on when `--level` isn't given
+const DEFAULT_LEVEL: &'static str = "level1.tmx";
+// Nanoseconds per frame with `--headless`, which runs as fast as it can
+// otherwise. A fixed step makes runs with the same seed and replay repeat.
+const HEADLESS_STEP: u32 = 16_666_667;
 
-        let offset = [-0.5, -0.5, 0.];
-        let phys_box = Cuboid::new(Vector::new(0.463, 0.463));
-        let mut l_trans = LocalTransform::default();
-        l_trans.translation = offset.clone();
-        let trans = Transform::default();
-
-        let dyn_parent =
-            world.create_now()
-                .with(LocalTransform::default())
-                .with(trans.clone())
-                .with(ImpulseComponent::default())
-                .with(
-                    PhysicsComponent::new(
-                        RigidBody::new_dynamic(
-                            phys_box.clone(),
-                            0.5,
-                            0.5,
-                            0.9,
-                        )
-                    )
-                )
-                .build();
-
-        world.create_now()
-            .with(l_trans)
-            .with(trans.clone())
-            .with(Child::new(dyn_parent))
-            .with(square.clone())
-            .build();
-
-        let mut l_trans = LocalTransform::default();
-        l_trans.translation = offset.clone();
-
-        let stat_parent =
-            world.create_now()
-                .with(LocalTransform::default())
-                .with(trans.clone())
-                .with(
-                    PhysicsComponent::with_position(
-                        RigidBody::new_static(
-                            phys_box.clone(),
-                            0.5,
-                            0.5,
-                        ),
-                        [0.8, 1.2],
-                    )
-                )
-                .build();
-
-        world.create_now()
-            .with(l_trans)
-            .with(trans.clone())
-            .with(Child::new(stat_parent))
-            .with(square.clone())
-            .build();
-
-        let mut l_trans = LocalTransform::default();
-        l_trans.translation = offset;
-
-        let stat_parent =
-            world.create_now()
-                .with(LocalTransform::default())
-                .with(trans)
-                .with(
-                    PhysicsComponent::with_position(
-                        RigidBody::new_static(
-                            phys_box.clone(),
-                            0.5,
-                            0.5,
-                        ),
-                        [0., 1.5],
-                    )
-                )
-                .build();
-
-        world.create_now()
-            .with(l_trans)
-            .with(trans.clone())
-            .with(Child::new(stat_parent))
-            .with(square.clone())
-            .build();
-    }
+fn main() {
+    let mut options = Options::from_args();
+    let paths       = AssetPaths::discover(&options);
+    // Quicksaves go next to the config file, like the display settings
+    let level       = Level::new(
+        &paths.root,
+        paths.config.parent().unwrap_or(Path::new("")),
+        options.level.as_ref().map_or(DEFAULT_LEVEL, |l| &l[..]),
+    );
 
-    fn update(
-        &mut self,
-        ctx: &mut Context,
-        _world: &mut World,
-    ) -> Trans {
-        // Exit if user hits Escape or closes the window
-        use amethyst::context::event::{EngineEvent, Event, VirtualKeyCode};
-        let engine_events = ctx.broadcaster.read::<EngineEvent>();
+    let replay = options.replay.as_ref().map(|path| {
+        Replay::load(path).unwrap_or_else(|e| {
+            let _ = writeln!(
+                io::stderr(),
+                "Cannot read {}: {}",
+                path.display(),
+                e
+            );
+            process::exit(1);
+        })
+    });
+
+    // A replay only plays out the same way with the seed it was recorded with
+    let rng = match options.seed
+        .or_else(|| replay.as_ref().and_then(Replay::seed))
+    {
+        Some(seed) => Rng::new(seed),
+        None       => Rng::from_time(),
+    };
+
+    let replay = match (replay, options.record.as_ref()) {
+        (Some(replay), _) => replay,
+        (None, Some(path)) => Replay::record(path, rng.seed())
+            .unwrap_or_else(|e| {
+                let _ = writeln!(
+                    io::stderr(),
+                    "Cannot write {}: {}",
+                    path.display(),
+                    e
+                );
+                process::exit(1);
+            }),
+        (None, None) => Replay::off(),
+    };
+
+    let restart    = Restart::default();
+    let mut replay = Some(replay);
+
+    // The engine can only make a window fullscreen when creating it, so the
+    // menu switches by asking for the game to be run again in a new one
+    loop {
+        // Input only goes to the first window. The menu is the only way to
+        // restart, and automated runs never show it.
+        let replay = replay.take().unwrap_or_else(Replay::off);
+        let rng    = rng.clone();
+        let failed = run(&options, &paths, &level, rng, replay, &restart);
+
+        if failed {
+            // So scripts and tests running `--frames` notice a broken build
+            process::exit(1);
+        }
 
-        for engine_event in engine_events.iter() {
-            match engine_event.payload {
-                Event::Closed |
-                Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) =>
-                    return Trans::Quit,
-                _ => (),
-            }
+        if !restart.take() {
+            break;
         }
 
-        Trans::None
+        // The menu saved the new setting, which this would otherwise override
+        options.fullscreen = None;
     }
 }
 
-fn main() {
+// Builds a context and window from the current settings, then runs the game in
+// it until it quits. Returns whether an automated run failed to load anything.
+fn run(
+    options: &Options,
+    paths: &AssetPaths,
+    level: &Level,
+    rng: Rng,
+    replay: Replay,
+    restart: &Restart,
+) -> bool {
     use loaders::obj::{MtlLib, MtlLoader};
+    use loaders::vertex::NormalMode;
     use amethyst::context::asset_manager::Mesh;
 
+    let settings = DisplaySettings::load(&paths.config);
+
     let mut config = ContextConfig::default();
-    config.display_config.backend = "OpenGL".into();
+    config.display_config.backend = if options.headless {
+        "Null".into()
+    } else {
+        "OpenGL".into()
+    };
     config.display_config.title   = "Hunting game".into();
+    settings.apply(&mut config);
+
+    // Only for this run, so these don't go through the settings
+    if let Some(fullscreen) = options.fullscreen {
+        config.display_config.fullscreen = fullscreen;
+    }
+
+    if let Some(resolution) = options.resolution {
+        config.display_config.dimensions =
+            Some((resolution[0], resolution[1]));
+    }
+
     let mut context = Context::new(config);
 
     context.asset_manager.register_asset::<Vec<Renderable>>();
     context.asset_manager.register_asset::<Texture>();
     context.asset_manager.register_asset::<MtlLib>();
+    context.asset_manager.register_asset::<ObjObjects>();
+    context.asset_manager.register_asset::<SpriteSheet>();
+    context.asset_manager.register_asset::<GltfScene>();
+    context.asset_manager.register_asset::<TiledMap>();
+    context.asset_manager.register_asset::<Prefab>();
+    context.asset_manager.register_asset::<MeshBatch>();
     context.asset_manager.register_asset::<Mesh>();
 
     context.asset_manager.register_loader::<MtlLib, MtlLoader>("mtl");
-    context.asset_manager.register_loader::<Texture, PngTextureLoader>("png");
+    context.asset_manager.add_loader(NormalMode::default());
+    {
+        use loaders::texture::{Png, Jpeg, Tga, Bmp, Gif, Dds};
+
+        context.asset_manager.add_loader(TextureOptions::default());
+
+        context.asset_manager
+            .register_loader::<Texture, ImageTextureLoader<Png>>("png");
+        context.asset_manager
+            .register_loader::<Texture, ImageTextureLoader<Jpeg>>("jpg");
+        context.asset_manager
+            .register_loader::<Texture, ImageTextureLoader<Jpeg>>("jpeg");
+        context.asset_manager
+            .register_loader::<Texture, ImageTextureLoader<Tga>>("tga");
+        context.asset_manager
+            .register_loader::<Texture, ImageTextureLoader<Bmp>>("bmp");
+        context.asset_manager
+            .register_loader::<Texture, ImageTextureLoader<Gif>>("gif");
+        context.asset_manager
+            .register_loader::<Texture, ImageTextureLoader<Dds>>("dds");
+    }
     context.asset_manager.register_loader::<Vec<Renderable>, ObjLoader>("obj");
+    context.asset_manager.register_loader::<ObjObjects, ObjLoader>("obj");
+    context.asset_manager.register_loader::<MeshBatch, ObjLoader>("obj");
+    for ext in &["gltf", "glb"] {
+        context.asset_manager
+            .register_loader::<Vec<Renderable>, GltfLoader>(ext);
+        context.asset_manager.register_loader::<GltfScene, GltfLoader>(ext);
+    }
+    context.asset_manager
+        .register_loader::<SpriteSheet, SpriteSheetLoader>("sheet");
+    context.asset_manager.register_loader::<TiledMap, TiledLoader>("tmx");
+    context.asset_manager.register_loader::<TiledMap, TiledLoader>("tmj");
+    context.asset_manager.register_loader::<Prefab, PrefabLoader>("prefab");
+
+    context.asset_manager.register_store(paths.store());
+    // So `loaders::load` can read dependencies before the assets needing them
+    context.asset_manager.add_loader(paths.clone());
+
+    // Loaders upload through the factory, which is a stand-in that keeps
+    // nothing when there's no window
+    if context.asset_manager.get_loader_mut::<FactoryImpl>().is_none() {
+        context.asset_manager.add_loader(FactoryImpl::Null);
+    }
 
-    let path = format!("{}/resources/assets/", env!("CARGO_MANIFEST_DIR"));
+    let brightness = Brightness::new(settings.brightness);
 
-    context.asset_manager.register_store(
-        DirectoryStore::new(&path)
-    );
+    // Nothing to draw to without a window
+    let render_prcs = if options.headless {
+        None
+    } else {
+        let render_prcs =
+            RenderingProcessor::new(Default::default(), &mut context);
 
-    let render_prcs = RenderingProcessor::new(
-        Default::default(),
-        &mut context,
-    );
+        brightness::install(&mut context, brightness.clone());
+
+        Some(render_prcs)
+    };
 
     let phys_process = PhysicsProcessor::new();
 
-    let mut game = Application::build(HelloWorld, context)
-        .with(render_prcs, "Rendering processor", 0)
+    context.broadcaster.register::<AnimationEvent>();
+
+    let map      = level.file();
+    let manifest = [&map[..]];
+
+    // Set by the loading screen and hunt when assets or the level fail to load
+    let failed = Rc::new(Cell::new(false));
+
+    // Automated runs have no one to pick from the menu
+    let loading = if options.headless || options.frames.is_some() {
+        let mut hunt = HuntState::new(level.clone())
+            .with_failure_flag(failed.clone());
+
+        if let Some(frames) = options.frames {
+            hunt = hunt.with_frame_limit(frames);
+        }
+
+        if options.headless {
+            hunt = hunt.with_fixed_step(Duration::new(0, HEADLESS_STEP));
+        }
+
+        LoadingState::new(paths.clone(), &manifest, hunt)
+            .with_failure_flag(failed.clone())
+    } else {
+        LoadingState::new(
+            paths.clone(),
+            &manifest,
+            MenuState::new(level.clone()),
+        )
+    };
+    let loading = loading
+        .with_resource(settings)
+        .with_resource(rng)
+        .with_resource(replay)
+        .with_resource(restart.clone());
+
+    let mut game = Application::build(loading, context);
+
+    if let Some(render_prcs) = render_prcs {
+        game = game.with(render_prcs, "Rendering processor", 0);
+    }
+
+    let mut game = game
         .register::<Renderable>()
         .register::<Light>()
         .register::<Camera>()
+        .with(CameraProcessor, "Camera processor", 1)
+        .with(ViewportProcessor, "Viewport processor", 1)
+        .with(BrightnessProcessor(brightness), "Brightness processor", 1)
+        .register::<CameraController>()
         .with(
             TransformProcessor::new(),
             "Transform processor",
@@ -303,8 +373,19 @@ fn main() {
         .with(phys_process, "Physics processor", 1)
         .register::<PhysicsComponent>()
         .register::<ImpulseComponent>()
+        .register::<MapObject>()
         .with(ImpulseProcessor, "Impulse processor", 2)
+        .with(InputProcessor, "Input processor", 2)
+        .with(AnimationProcessor, "Animation processor", 1)
+        .register::<SpriteAnimation>()
+        .with(
+            HotReloadProcessor::new(paths.layers()),
+            "Hot reload processor",
+            3,
+        )
         .done();
 
     game.run();
+
+    failed.get()
 }
@@ -0,0 +1,210 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use amethyst::context::Context;
+use amethyst::context::event::{
+    ElementState,
+    EngineEvent,
+    Event,
+    VirtualKeyCode,
+};
+
+// Keys the hunt and its pause screen react to. Nothing else is recorded.
+const KEYS: &'static [(&'static str, VirtualKeyCode)] = &[
+    ("Up",     VirtualKeyCode::Up),
+    ("Down",   VirtualKeyCode::Down),
+    ("Left",   VirtualKeyCode::Left),
+    ("Right",  VirtualKeyCode::Right),
+    ("Escape", VirtualKeyCode::Escape),
+    ("Q",      VirtualKeyCode::Q),
+    ("F5",     VirtualKeyCode::F5),
+    ("F9",     VirtualKeyCode::F9),
+];
+
+#[derive(Clone, Copy, Debug)]
+struct KeyEvent {
+    frame: u64,
+    state: ElementState,
+    key:   VirtualKeyCode,
+}
+
+enum Mode {
+    Off,
+    Record(BufWriter<File>),
+    Play(VecDeque<KeyEvent>),
+}
+
+// World resource for `--record` and `--replay`. The file starts with the seed
+// the run used, followed by a line per key event:
+//
+//     seed 1234
+//     12 pressed Left
+//     20 released Left
+//
+// Frames only count while the hunt or its pause screen is running, as those
+// call `step`, so time spent loading and in menus doesn't matter. Physics steps
+// by how long each frame took, so a replay only ends where its recording did if
+// frame times match too, as they do with `--headless`.
+pub struct Replay {
+    mode:  Mode,
+    seed:  Option<u64>,
+    frame: u64,
+}
+
+impl Replay {
+    pub fn off() -> Self {
+        Replay {
+            mode:  Mode::Off,
+            seed:  None,
+            frame: 0,
+        }
+    }
+
+    pub fn record(path: &Path, seed: u64) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+
+        writeln!(out, "seed {}", seed)?;
+
+        Ok(Replay {
+            mode:  Mode::Record(out),
+            seed:  Some(seed),
+            frame: 0,
+        })
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let file       = BufReader::new(File::open(path)?);
+        let mut replay = Replay {
+            mode:  Mode::Off,
+            seed:  None,
+            frame: 0,
+        };
+        let mut events = VecDeque::new();
+
+        for (i, line) in file.lines().enumerate() {
+            let line  = line?;
+            let words = line.split_whitespace().collect::<Vec<_>>();
+
+            let invalid = || io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Line {}: cannot parse \"{}\"", i + 1, line),
+            );
+
+            if words.is_empty() {
+                continue;
+            }
+
+            if words.len() == 2 && words[0] == "seed" {
+                replay.seed = Some(words[1].parse().map_err(|_| invalid())?);
+                continue;
+            }
+
+            if words.len() != 3 {
+                return Err(invalid());
+            }
+
+            events.push_back(KeyEvent {
+                frame: words[0].parse().map_err(|_| invalid())?,
+                state: match words[1] {
+                    "pressed"  => ElementState::Pressed,
+                    "released" => ElementState::Released,
+                    _          => return Err(invalid()),
+                },
+                key:   KEYS.iter()
+                    .find(|&&(name, _)| name == words[2])
+                    .map(|&(_, key)| key)
+                    .ok_or_else(|| invalid())?,
+            });
+        }
+
+        replay.mode = Mode::Play(events);
+
+        Ok(replay)
+    }
+
+    // The seed the replay was recorded with, if it was loaded from a file
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    // Called once a frame, before the state reads its events. Publishes the
+    // events for this frame when playing back, or writes out this frame's
+    // events when recording.
+    pub fn step(&mut self, ctx: &mut Context) {
+        let frame = self.frame;
+        self.frame += 1;
+
+        let failed = match self.mode {
+            Mode::Off => false,
+            Mode::Record(ref mut out) => match write_events(out, frame, ctx) {
+                Ok(()) => false,
+                Err(e) => {
+                    let _ = writeln!(
+                        io::stderr(),
+                        "Cannot record input, stopping: {}",
+                        e
+                    );
+
+                    true
+                },
+            },
+            Mode::Play(ref mut events) => {
+                while events.front().map_or(false, |e| e.frame <= frame) {
+                    let event = events.pop_front().unwrap();
+
+                    ctx.broadcaster.publish()
+                        .with(EngineEvent::new(Event::KeyboardInput(
+                            event.state,
+                            0,
+                            Some(event.key),
+                        )))
+                        .build();
+                }
+
+                false
+            },
+        };
+
+        if failed {
+            self.mode = Mode::Off;
+        }
+    }
+}
+
+fn write_events<W: Write>(
+    out: &mut W,
+    frame: u64,
+    ctx: &Context,
+) -> io::Result<()> {
+    let engine_events = ctx.broadcaster.read::<EngineEvent>();
+    let mut written   = false;
+
+    for engine_event in engine_events.iter() {
+        let (state, key) = match engine_event.payload {
+            Event::KeyboardInput(state, _, Some(key)) => (state, key),
+            _                                         => continue,
+        };
+
+        let name = match KEYS.iter().find(|&&(_, k)| k == key) {
+            Some(&(name, _)) => name,
+            None             => continue,
+        };
+
+        let state = match state {
+            ElementState::Pressed  => "pressed",
+            ElementState::Released => "released",
+        };
+
+        writeln!(out, "{} {} {}", frame, state, name)?;
+        written = true;
+    }
+
+    // So a crash loses at most a frame
+    if written {
+        out.flush()?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// World resource for anything random in gameplay. This is xorshift64*, which
+// gives the same numbers for the same seed on every platform, so a run can be
+// repeated with `--seed`.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    seed:  u64,
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng {
+            seed:  seed,
+            // Xorshift never leaves zero
+            state: if seed == 0 { 0x9e37_79b9_7f4a_7c15 } else { seed },
+        }
+    }
+
+    // Seeded from the clock, for when a run doesn't need to be repeatable
+    pub fn from_time() -> Self {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() ^ ((d.subsec_nanos() as u64) << 32))
+            .unwrap_or(0);
+
+        Self::new(now)
+    }
+
+    // What this was created with, to give to `--seed` to get the same numbers
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    // In `[0, 1)`
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    // In `[low, high)`
+    pub fn range(&mut self, low: f32, high: f32) -> f32 {
+        low + (high - low) * self.next_f32()
+    }
+}
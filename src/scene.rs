@@ -0,0 +1,254 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use amethyst::ecs::{Entity, Join, World};
+use amethyst::processors::rendering::Renderable;
+use amethyst::processors::transform::{Child, LocalTransform, Transform};
+use yaml_rust::Yaml;
+
+use loaders::{split_file, LoaderError};
+use loaders::prefab::{body_to_yaml, parse_body, parse_transform};
+use loaders::yaml;
+use systems::physics::{quaternion_angle, BodyDesc};
+use systems::physics::{ImpulseComponent, PhysicsComponent};
+
+// Scenes are every entity with a `LocalTransform`, written out as YAML using
+// the same keys as prefabs:
+//
+//     entities:
+//         - id: 0
+//           transform: { translation: [0, 0, 0], rotation: [1, 0, 0, 0] }
+//           physics: { shape: box, size: [0.926, 0.926], dynamic: true }
+//           impulse: true
+//         - id: 1
+//           parent: 0
+//           transform: { translation: [-0.5, -0.5, 0] }
+//           renderable: { mesh: Quad_face, ambient: default, diffuse: default }
+//
+// Ids only mean something inside the file, loaded entities get fresh ones.
+// Renderables are saved by asset name, so whatever they use has to be loaded
+// before the scene is. Physics bodies come back at rest where they were saved,
+// and bodies that weren't built from a `BodyDesc` can't be saved at all.
+
+struct Entry {
+    id:         i64,
+    parent:     Option<i64>,
+    transform:  ([f32; 3], [f32; 4], [f32; 3]),
+    renderable: Option<Renderable>,
+    body:       Option<BodyDesc>,
+    impulse:    bool,
+}
+
+// Everything `to_yaml` would save
+pub fn entities(world: &World) -> Vec<Entity> {
+    let entities = world.entities();
+    let l_trans  = world.read::<LocalTransform>();
+
+    (&entities, &l_trans).iter().map(|(entity, _)| entity).collect()
+}
+
+pub fn to_yaml(world: &World) -> Yaml {
+    let entities    = world.entities();
+    let l_trans     = world.read::<LocalTransform>();
+    let children    = world.read::<Child>();
+    let renderables = world.read::<Renderable>();
+    let physics     = world.read::<PhysicsComponent>();
+    let impulses    = world.read::<ImpulseComponent>();
+
+    let saved = (&entities, &l_trans).iter().collect::<Vec<_>>();
+    let ids   = saved.iter()
+        .enumerate()
+        .map(|(id, &(entity, _))| (entity, id as i64))
+        .collect::<HashMap<_, _>>();
+
+    let entries = saved.iter().map(|&(entity, trans)| {
+        let mut pairs = vec![
+            ("id", Yaml::Integer(ids[&entity])),
+            ("transform", yaml::hash(vec![
+                ("translation", yaml::reals(&trans.translation)),
+                ("rotation", yaml::reals(&trans.rotation)),
+                ("scale", yaml::reals(&trans.scale)),
+            ])),
+        ];
+
+        // Parents without a transform aren't saved, so neither is the link
+        let parent = children.get(entity).and_then(|c| ids.get(&c.parent()));
+
+        if let Some(&parent) = parent {
+            pairs.push(("parent", Yaml::Integer(parent)));
+        }
+
+        if let Some(rend) = renderables.get(entity) {
+            pairs.push(("renderable", yaml::hash(vec![
+                ("mesh", Yaml::String(rend.mesh.clone())),
+                ("ambient", Yaml::String(rend.ka.clone())),
+                ("diffuse", Yaml::String(rend.kd.clone())),
+            ])));
+        }
+
+        match physics.get(entity).map(PhysicsComponent::desc) {
+            Some(Some(body)) => pairs.push(("physics", body_to_yaml(body))),
+            Some(None)       => {
+                let _ = writeln!(
+                    io::stderr(),
+                    "[scene] skipping a physics body without a description"
+                );
+            },
+            None => (),
+        }
+
+        if impulses.get(entity).is_some() {
+            pairs.push(("impulse", Yaml::Boolean(true)));
+        }
+
+        yaml::hash(pairs)
+    }).collect();
+
+    yaml::hash(vec![("entities", Yaml::Array(entries))])
+}
+
+pub fn save(world: &World, path: &Path) -> io::Result<()> {
+    yaml::write_file(path, &to_yaml(world))
+}
+
+fn entry(yaml: &Yaml) -> Result<Entry, LoaderError> {
+    let renderable = match yaml["renderable"] {
+        Yaml::BadValue => None,
+        ref rend       => Some(Renderable::new(
+            yaml::require(rend, "mesh", Yaml::as_str)?,
+            yaml::require(rend, "ambient", Yaml::as_str)?,
+            yaml::require(rend, "diffuse", Yaml::as_str)?,
+        )),
+    };
+
+    Ok(Entry {
+        id:         yaml::require(yaml, "id", Yaml::as_i64)?,
+        parent:     yaml["parent"].as_i64(),
+        transform:  parse_transform(&yaml["transform"])?,
+        renderable: renderable,
+        body:       parse_body(&yaml["physics"])?,
+        impulse:    yaml["impulse"].as_bool().unwrap_or(false),
+    })
+}
+
+// Everything is checked before the first entity is created, so a bad file
+// leaves the world as it was.
+fn check(entries: &[Entry]) -> Result<(), LoaderError> {
+    let parents = entries.iter()
+        .map(|e| (e.id, e.parent))
+        .collect::<HashMap<_, _>>();
+
+    if parents.len() != entries.len() {
+        return Err(LoaderError::parse(None, "duplicate entity ids"));
+    }
+
+    for entry in entries {
+        let mut seen    = HashSet::new();
+        let mut current = entry.parent;
+
+        while let Some(parent) = current {
+            if !seen.insert(parent) {
+                return Err(LoaderError::parse(
+                    None,
+                    format!("entity {} is its own ancestor", entry.id),
+                ));
+            }
+
+            current = *parents.get(&parent).ok_or_else(|| LoaderError::parse(
+                None,
+                format!("entity {} has unknown parent {}", entry.id, parent),
+            ))?;
+        }
+    }
+
+    Ok(())
+}
+
+// Recreates the scene's entities, returning them in file order
+pub fn from_yaml(
+    world: &mut World,
+    doc: &Yaml,
+) -> Result<Vec<Entity>, LoaderError> {
+    let entries = yaml::require(doc, "entities", Yaml::as_vec)?
+        .iter()
+        .map(entry)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    check(&entries)?;
+
+    let mut remap    = HashMap::new();
+    let mut entities = vec![];
+
+    for entry in &entries {
+        let (translation, rotation, scale) = entry.transform;
+
+        let mut l_trans = LocalTransform::default();
+        l_trans.translation = translation;
+        l_trans.rotation    = rotation;
+        l_trans.scale       = scale;
+
+        let mut builder = world.create_now()
+            .with(l_trans)
+            .with(Transform::default());
+
+        if let Some(ref rend) = entry.renderable {
+            builder = builder.with(rend.clone());
+        }
+
+        if let Some(body) = entry.body {
+            builder = builder.with(
+                PhysicsComponent::from_desc(
+                    body,
+                    [translation[0], translation[1]],
+                ).rotated(quaternion_angle(rotation))
+            );
+        }
+
+        if entry.impulse {
+            builder = builder.with(ImpulseComponent::default());
+        }
+
+        let entity = builder.build();
+
+        remap.insert(entry.id, entity);
+        entities.push(entity);
+    }
+
+    // Parents can come after their children in the file, so links are only
+    // made once every entity exists.
+    let mut children = world.write::<Child>();
+
+    for entry in &entries {
+        if let Some(parent) = entry.parent {
+            children.insert(remap[&entry.id], Child::new(remap[&parent]));
+        }
+    }
+
+    Ok(entities)
+}
+
+pub fn load(
+    world: &mut World,
+    data: &[u8],
+) -> Result<Vec<Entity>, LoaderError> {
+    from_yaml(world, &yaml::parse(data)?)
+}
+
+pub fn load_file(
+    world: &mut World,
+    path: &Path,
+) -> Result<Vec<Entity>, LoaderError> {
+    let mut data = vec![];
+    let file     = path.to_string_lossy();
+
+    File::open(path)
+        .and_then(|mut f| f.read_to_end(&mut data))
+        .map_err(|_| {
+            let (name, ext) = split_file(&file, "");
+            LoaderError::missing(name, ext)
+        })?;
+
+    load(world, &data)
+}
@@ -0,0 +1,134 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use amethyst::context::ContextConfig;
+use yaml_rust::Yaml;
+
+use loaders::yaml;
+
+// Colours are multiplied by the brightness in the fragment shader, see
+// `systems::brightness`, so anything above 1 washes out
+pub const MAX_BRIGHTNESS: f32 = 2.;
+
+// The `display` section of config.yml:
+//
+//     display:
+//         brightness: 1.0             # 0 is black, 1 leaves colours alone, 2
+//                                     # doubles them
+//         fullscreen: false
+//         resolution: [1024, 768]
+//
+// Applied to the context config whenever a window is made, and kept in the
+// world as a resource so the menu can change it while the game runs. The
+// engine only reads `fullscreen` when it creates the window, so the menu
+// switches it by asking for a new one with `Restart`.
+#[derive(Clone, Debug)]
+pub struct DisplaySettings {
+    pub brightness: f32,
+    pub fullscreen: bool,
+    pub resolution: [u32; 2],
+    // Where `save` writes back to
+    path:           PathBuf,
+}
+
+impl DisplaySettings {
+    pub fn new(path: &Path) -> Self {
+        DisplaySettings {
+            brightness: 1.,
+            fullscreen: false,
+            resolution: [1024, 768],
+            path:       path.into(),
+        }
+    }
+
+    // Falls back to the defaults for anything missing or invalid
+    pub fn load(path: &Path) -> Self {
+        let config = match yaml::read_file(path) {
+            Ok(config) => config,
+            Err(e)     => {
+                let _ = writeln!(
+                    io::stderr(),
+                    "Cannot read {}: {}",
+                    path.display(),
+                    e
+                );
+
+                Yaml::Null
+            },
+        };
+
+        let default = Self::new(path);
+        let display = &config["display"];
+
+        let resolution = yaml::pair(&display["resolution"])
+            .and_then(|r| if r[0] >= 1. && r[1] >= 1. {
+                Some([r[0] as u32, r[1] as u32])
+            } else {
+                None
+            });
+
+        DisplaySettings {
+            brightness: yaml::number(&display["brightness"])
+                .map_or(default.brightness, |b| b.max(0.).min(MAX_BRIGHTNESS)),
+            fullscreen: display["fullscreen"].as_bool()
+                .unwrap_or(default.fullscreen),
+            resolution: resolution.unwrap_or(default.resolution),
+            ..default
+        }
+    }
+
+    pub fn apply(&self, config: &mut ContextConfig) {
+        config.display_config.fullscreen = self.fullscreen;
+        config.display_config.dimensions =
+            Some((self.resolution[0], self.resolution[1]));
+    }
+
+    pub fn to_yaml(&self) -> Yaml {
+        yaml::hash(vec![
+            ("brightness", yaml::real(self.brightness)),
+            ("fullscreen", Yaml::Boolean(self.fullscreen)),
+            ("resolution", Yaml::Array(vec![
+                Yaml::Integer(self.resolution[0] as i64),
+                Yaml::Integer(self.resolution[1] as i64),
+            ])),
+        ])
+    }
+
+    // Replaces the `display` section of the file it was loaded from, keeping
+    // every other setting. Comments are lost, since the emitter can't write
+    // them.
+    pub fn save(&self) -> io::Result<()> {
+        let config = match yaml::read_file(&self.path) {
+            Ok(config) => config,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Yaml::Null,
+            // Better to not save than to replace a file we couldn't read
+            Err(e) => return Err(e),
+        };
+
+        yaml::write_file(
+            &self.path,
+            &yaml::merge(&config, &yaml::hash(vec![
+                ("display", self.to_yaml()),
+            ])),
+        )
+    }
+}
+
+// World resource for states to ask `main` to build a new context and window
+// once the game quits, and run it again from the start. The flag is shared
+// with `main`, which outlives the world.
+#[derive(Clone, Debug, Default)]
+pub struct Restart(Arc<AtomicBool>);
+
+impl Restart {
+    pub fn request(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    // Clears the request, so the next run doesn't restart on its own
+    pub fn take(&self) -> bool {
+        self.0.swap(false, Ordering::SeqCst)
+    }
+}
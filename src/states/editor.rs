@@ -0,0 +1,409 @@
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use amethyst::context::Context;
+use amethyst::context::event::{
+    ElementState,
+    EngineEvent,
+    Event,
+    MouseButton,
+    VirtualKeyCode,
+};
+use amethyst::ecs::{Entity, Join, World};
+use amethyst::engine::{State, Trans};
+use amethyst::processors::transform::{Child, LocalTransform};
+use yaml_rust::Yaml;
+
+use loaders::prefab::spawn_prefab;
+use scene;
+use systems::input::Mouse;
+use systems::physics::{quaternion_angle, BodyDesc, Collider};
+use systems::physics::{PhysicsComponent, PhysicsPaused};
+
+// Size change per press of `[` or `]`
+const SCALE_STEP:    f32 = 1.1;
+const MATERIAL_STEP: f32 = 0.1;
+
+// Pushed over gameplay to lay out levels, with physics paused while it's open.
+//
+//     left mouse      select and drag
+//     1 - 9           spawn the nth prefab at the cursor
+//     delete          delete the selection and its children
+//     D               toggle between static and dynamic
+//     B               toggle between box and ball colliders
+//     [ ]             shrink or grow the collider, and the model with it
+//     - =             lower or raise friction
+//     , .             lower or raise restitution
+//     ctrl + S        save to the scene file
+//     F1              back to the game
+//
+// Only what has a physics body can be selected, see `Mouse::hovered`. There's
+// no way to draw text yet, so edits are reported on stderr.
+pub struct EditorState {
+    scene:    PathBuf,
+    prefabs:  Vec<String>,
+    // In world space, as of the last frame the mouse was over the window
+    cursor:   [f32; 2],
+    ctrl:     bool,
+    selected: Option<Entity>,
+    // Offset from the cursor to the selection while it's being dragged
+    drag:     Option<[f32; 2]>,
+    // Whether physics was paused before the editor was opened
+    paused:   bool,
+}
+
+impl EditorState {
+    pub fn new(scene: PathBuf, prefabs: &[&str]) -> Self {
+        EditorState {
+            scene:    scene,
+            prefabs:  prefabs.iter().map(|p| p.to_string()).collect(),
+            cursor:   [0.; 2],
+            ctrl:     false,
+            selected: None,
+            drag:     None,
+            paused:   false,
+        }
+    }
+
+    // Left click selects whatever is under the cursor, and dragging moves it
+    fn mouse(&mut self, world: &mut World) {
+        let (position, cursor, hovered, pressed, released, down) = {
+            let mouse = world.read_resource::<Mouse>();
+
+            (
+                mouse.position,
+                mouse.world,
+                mouse.hovered,
+                mouse.was_pressed(MouseButton::Left),
+                mouse.was_released(MouseButton::Left),
+                mouse.is_down(MouseButton::Left),
+            )
+        };
+
+        let moved = match cursor {
+            Some(cursor) if cursor != self.cursor => {
+                self.cursor = cursor;
+                true
+            },
+            _ => false,
+        };
+
+        if pressed {
+            // Bodies spawned or moved since the physics last ran aren't in the
+            // simulation yet, so aren't hovered
+            let entity = hovered.or_else(|| super::pick(world, position));
+
+            self.select(world, entity);
+        }
+
+        if released || !down {
+            self.drag = None;
+        }
+
+        if !moved {
+            return;
+        }
+
+        if let (Some(entity), Some(offset)) = (self.selected, self.drag) {
+            move_to(world, entity, [
+                self.cursor[0] + offset[0],
+                self.cursor[1] + offset[1],
+            ]);
+        }
+    }
+
+    fn select(&mut self, world: &World, entity: Option<Entity>) {
+        self.selected = entity;
+        self.drag     = entity.and_then(|entity| {
+            let l_trans = world.read::<LocalTransform>();
+
+            l_trans.get(entity).map(|trans| [
+                trans.translation[0] - self.cursor[0],
+                trans.translation[1] - self.cursor[1],
+            ])
+        });
+
+        if let Some(entity) = entity {
+            report(world, entity, "selected");
+        }
+    }
+
+    fn spawn(&mut self, ctx: &mut Context, world: &mut World, index: usize) {
+        let name = match self.prefabs.get(index) {
+            Some(name) => name.clone(),
+            None       => return,
+        };
+
+        match spawn_prefab(
+            &mut ctx.asset_manager,
+            world,
+            &name,
+            self.cursor,
+            &Yaml::Null,
+        ) {
+            Ok(entity) => {
+                self.selected = Some(entity);
+                report(world, entity, "spawned");
+            },
+            Err(e) => {
+                let _ = writeln!(
+                    io::stderr(),
+                    "[editor] cannot spawn {}: {}",
+                    name,
+                    e
+                );
+            },
+        }
+    }
+
+    fn key(
+        &mut self,
+        ctx: &mut Context,
+        world: &mut World,
+        key: VirtualKeyCode,
+    ) -> Trans {
+        let selected = self.selected;
+
+        match key {
+            VirtualKeyCode::F1 => return Trans::Pop,
+            VirtualKeyCode::S if self.ctrl => {
+                match scene::save(world, &self.scene) {
+                    Ok(()) => {
+                        let _ = writeln!(
+                            io::stderr(),
+                            "[editor] saved {}",
+                            self.scene.display()
+                        );
+                    },
+                    Err(e) => {
+                        let _ = writeln!(
+                            io::stderr(),
+                            "[editor] cannot save {}: {}",
+                            self.scene.display(),
+                            e
+                        );
+                    },
+                }
+            },
+            VirtualKeyCode::Key1 => self.spawn(ctx, world, 0),
+            VirtualKeyCode::Key2 => self.spawn(ctx, world, 1),
+            VirtualKeyCode::Key3 => self.spawn(ctx, world, 2),
+            VirtualKeyCode::Key4 => self.spawn(ctx, world, 3),
+            VirtualKeyCode::Key5 => self.spawn(ctx, world, 4),
+            VirtualKeyCode::Key6 => self.spawn(ctx, world, 5),
+            VirtualKeyCode::Key7 => self.spawn(ctx, world, 6),
+            VirtualKeyCode::Key8 => self.spawn(ctx, world, 7),
+            VirtualKeyCode::Key9 => self.spawn(ctx, world, 8),
+            _ => if let Some(entity) = selected {
+                self.edit(world, entity, key);
+            },
+        }
+
+        Trans::None
+    }
+
+    fn edit(
+        &mut self,
+        world: &mut World,
+        entity: Entity,
+        key: VirtualKeyCode,
+    ) {
+        match key {
+            VirtualKeyCode::Delete => {
+                delete_tree(world, entity);
+
+                self.selected = None;
+                self.drag     = None;
+
+                return;
+            },
+            VirtualKeyCode::D => edit_body(world, entity, |body, _| {
+                body.dynamic = !body.dynamic;
+            }),
+            VirtualKeyCode::B => edit_body(world, entity, |body, _| {
+                body.collider = match body.collider {
+                    Collider::Box(size) =>
+                        Collider::Ball(size[0].max(size[1]) / 2.),
+                    Collider::Ball(radius) =>
+                        Collider::Box([radius * 2.; 2]),
+                };
+            }),
+            VirtualKeyCode::LBracket => edit_body(world, entity, |body, t| {
+                scale(body, t, 1. / SCALE_STEP);
+            }),
+            VirtualKeyCode::RBracket => edit_body(world, entity, |body, t| {
+                scale(body, t, SCALE_STEP);
+            }),
+            VirtualKeyCode::Minus => edit_body(world, entity, |body, _| {
+                body.friction = (body.friction - MATERIAL_STEP).max(0.);
+            }),
+            VirtualKeyCode::Equals => edit_body(world, entity, |body, _| {
+                body.friction += MATERIAL_STEP;
+            }),
+            VirtualKeyCode::Comma => edit_body(world, entity, |body, _| {
+                body.restitution =
+                    (body.restitution - MATERIAL_STEP).max(0.);
+            }),
+            VirtualKeyCode::Period => edit_body(world, entity, |body, _| {
+                body.restitution =
+                    (body.restitution + MATERIAL_STEP).min(1.);
+            }),
+            _ => return,
+        }
+
+        report(world, entity, "edited");
+    }
+}
+
+impl State for EditorState {
+    fn on_start(&mut self, _ctx: &mut Context, world: &mut World) {
+        let mut paused = world.write_resource::<PhysicsPaused>();
+
+        self.paused = paused.0;
+        paused.0    = true;
+    }
+
+    fn on_stop(&mut self, _ctx: &mut Context, world: &mut World) {
+        world.write_resource::<PhysicsPaused>().0 = self.paused;
+
+        self.selected = None;
+        self.drag     = None;
+    }
+
+    fn update(&mut self, ctx: &mut Context, world: &mut World) -> Trans {
+        self.mouse(world);
+
+        let events = ctx.broadcaster.read::<EngineEvent>()
+            .iter()
+            .map(|e| e.payload.clone())
+            .collect::<Vec<_>>();
+
+        for event in events {
+            match event {
+                Event::Closed => return Trans::Quit,
+                Event::KeyboardInput(state, _, Some(key))
+                    if key == VirtualKeyCode::LControl ||
+                        key == VirtualKeyCode::RControl =>
+                    self.ctrl = state == ElementState::Pressed,
+                Event::KeyboardInput(ElementState::Pressed, _, Some(key)) => {
+                    match self.key(ctx, world, key) {
+                        Trans::None => (),
+                        trans       => return trans,
+                    }
+                },
+                _ => (),
+            }
+        }
+
+        Trans::None
+    }
+}
+
+// Bodies own their position once they're in the simulation, so moving one
+// means replacing it. The processor takes the old body out of the world.
+fn move_to(world: &mut World, entity: Entity, position: [f32; 2]) {
+    let rotation = {
+        let mut l_trans = world.write::<LocalTransform>();
+
+        match l_trans.get_mut(entity) {
+            Some(trans) => {
+                trans.translation[0] = position[0];
+                trans.translation[1] = position[1];
+
+                trans.rotation
+            },
+            None => return,
+        }
+    };
+
+    let mut physics = world.write::<PhysicsComponent>();
+    let desc        = physics.get(entity).and_then(|p| p.desc().cloned());
+
+    if let Some(desc) = desc {
+        physics.insert(
+            entity,
+            PhysicsComponent::from_desc(desc, position)
+                .rotated(quaternion_angle(rotation)),
+        );
+    }
+}
+
+fn edit_body<F>(world: &mut World, entity: Entity, edit: F)
+    where F: FnOnce(&mut BodyDesc, &mut LocalTransform)
+{
+    let mut l_trans = world.write::<LocalTransform>();
+    let mut physics = world.write::<PhysicsComponent>();
+
+    let desc = physics.get(entity).and_then(|p| p.desc().cloned());
+
+    if let (Some(mut desc), Some(trans)) = (desc, l_trans.get_mut(entity)) {
+        edit(&mut desc, trans);
+
+        physics.insert(
+            entity,
+            PhysicsComponent::from_desc(
+                desc,
+                [trans.translation[0], trans.translation[1]],
+            ).rotated(quaternion_angle(trans.rotation)),
+        );
+    }
+}
+
+fn scale(body: &mut BodyDesc, trans: &mut LocalTransform, factor: f32) {
+    body.collider = match body.collider {
+        Collider::Box(size) =>
+            Collider::Box([size[0] * factor, size[1] * factor]),
+        Collider::Ball(radius) => Collider::Ball(radius * factor),
+    };
+
+    trans.scale[0] *= factor;
+    trans.scale[1] *= factor;
+}
+
+fn delete_tree(world: &mut World, root: Entity) {
+    let doomed = {
+        let entities = world.entities();
+        let children = world.read::<Child>();
+
+        let mut doomed = vec![root];
+
+        loop {
+            let found = (&entities, &children).iter()
+                .filter(|&(entity, child)|
+                    doomed.contains(&child.parent()) &&
+                        !doomed.contains(&entity)
+                )
+                .map(|(entity, _)| entity)
+                .collect::<Vec<_>>();
+
+            if found.is_empty() {
+                break;
+            }
+
+            doomed.extend(found);
+        }
+
+        doomed
+    };
+
+    for entity in doomed {
+        world.delete_now(entity);
+    }
+}
+
+fn report(world: &World, entity: Entity, what: &str) {
+    let l_trans = world.read::<LocalTransform>();
+    let physics = world.read::<PhysicsComponent>();
+
+    let position = l_trans.get(entity).map(|t| t.translation);
+    let body     = physics.get(entity).and_then(|p| p.desc().cloned());
+
+    let _ = writeln!(
+        io::stderr(),
+        "[editor] {} {:?} at {:?}: {:?}",
+        what,
+        entity,
+        position,
+        body
+    );
+}
@@ -0,0 +1,331 @@
+use std::cell::Cell;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+
+use amethyst::context::Context;
+use amethyst::context::event::{
+    ElementState,
+    EngineEvent,
+    Event,
+    VirtualKeyCode,
+};
+use amethyst::ecs::{Entity, Join, World};
+use amethyst::engine::{State, Trans};
+use amethyst::processors::transform::LocalTransform;
+
+use loaders;
+use replay::Replay;
+use scene;
+use systems::camera::CameraController;
+use systems::physics::ImpulseComponent;
+use super::{EditorState, HuntResults, PauseState, ResultsState};
+
+const QUICKSAVE: &'static str = "quicksave.scene";
+const PREFABS:   &'static [&'static str] = &["box"];
+// Seconds until the hunt is over
+pub const TIME_LIMIT: f32 = 90.;
+
+// A map to hunt on, picked with `--level`
+#[derive(Clone, Debug)]
+pub struct Level {
+    // File name relative to the asset root, without the extension
+    pub map:       String,
+    pub ext:       String,
+    // Written by the editor, and used instead of the map when it exists
+    pub scene:     PathBuf,
+    // Written with F5 and read back with F9
+    pub quicksave: PathBuf,
+}
+
+impl Level {
+    // `file` is relative to the asset root, as `tmx` if it has no extension.
+    // The scene goes next to it, under `root`, and the quicksave in `saves`
+    // rather than wherever the game happened to be started from.
+    pub fn new(root: &Path, saves: &Path, file: &str) -> Self {
+        let file = Path::new(file);
+        let map  = file.with_extension("");
+
+        Level {
+            map:       map.to_string_lossy().into_owned(),
+            ext:       file.extension()
+                .map_or("tmx".into(), |e| e.to_string_lossy().into_owned()),
+            scene:     root.join(&map).with_extension("scene"),
+            quicksave: saves.join(QUICKSAVE),
+        }
+    }
+
+    pub fn file(&self) -> String {
+        format!("{}.{}", self.map, self.ext)
+    }
+}
+
+// A single hunt. Escape pauses, F1 opens the editor, and F5 and F9 quicksave
+// and quickload.
+pub struct HuntState {
+    level:    Level,
+    // Quit after this many frames, for `--frames`
+    frames:   Option<u64>,
+    frame:    u64,
+    // Used instead of the time each frame took, for `--headless`
+    step:     Option<Duration>,
+    camera:   Option<Entity>,
+    // Of the map, for the camera
+    bounds:   Option<([f32; 2], [f32; 2])>,
+    time:     f32,
+    distance: f32,
+    // Where the player was last frame
+    last:     Option<[f32; 2]>,
+    // Set by the pause screen when the player gives up
+    ended:    Rc<Cell<bool>>,
+    // Set if the level can't be loaded, see `with_failure_flag`
+    failed:   Option<Rc<Cell<bool>>>,
+}
+
+impl HuntState {
+    pub fn new(level: Level) -> Self {
+        HuntState {
+            level:    level,
+            frames:   None,
+            frame:    0,
+            step:     None,
+            camera:   None,
+            bounds:   None,
+            time:     0.,
+            distance: 0.,
+            last:     None,
+            ended:    Rc::new(Cell::new(false)),
+            failed:   None,
+        }
+    }
+
+    pub fn with_frame_limit(mut self, frames: u64) -> Self {
+        self.frames = Some(frames);
+        self
+    }
+
+    pub fn with_fixed_step(mut self, step: Duration) -> Self {
+        self.step = Some(step);
+        self
+    }
+
+    // As `LoadingState::with_failure_flag`
+    pub fn with_failure_flag(mut self, failed: Rc<Cell<bool>>) -> Self {
+        self.failed = Some(failed);
+        self
+    }
+
+    fn spawn_level(&mut self, ctx: &mut Context, world: &mut World) {
+        let assets = &mut ctx.asset_manager;
+        let level  = &self.level;
+
+        let map = match loaders::tiled::load_map(assets, &level.map, &level.ext)
+        {
+            Ok(map) => map,
+            Err(e)  => {
+                let _ = writeln!(
+                    io::stderr(),
+                    "Cannot load {}: {}",
+                    level.file(),
+                    e
+                );
+
+                if let Some(ref failed) = self.failed {
+                    failed.set(true);
+                }
+
+                return;
+            },
+        };
+
+        self.bounds = Some(map.bounds());
+
+        if level.scene.is_file() {
+            match scene::load_file(world, &level.scene) {
+                Ok(_)  => return,
+                Err(e) => {
+                    let _ = writeln!(
+                        io::stderr(),
+                        "Cannot load {}: {}",
+                        level.scene.display(),
+                        e
+                    );
+                },
+            }
+        }
+
+        map.spawn(assets, world);
+    }
+
+    // Points the camera at the player, which has to be redone whenever the
+    // player's entity is replaced
+    fn follow_player(&self, world: &mut World) {
+        let camera = match self.camera {
+            Some(camera) => camera,
+            None         => return,
+        };
+
+        let player = {
+            let entities = world.entities();
+            let impulses = world.read::<ImpulseComponent>();
+
+            (&entities, &impulses).iter().map(|(entity, _)| entity).next()
+        };
+
+        if let Some(player) = player {
+            let mut controller = CameraController::new(player);
+
+            if let Some((min, max)) = self.bounds {
+                controller = controller.with_bounds(min, max);
+            }
+
+            world.write::<CameraController>().insert(camera, controller);
+        }
+    }
+
+    // The player is whatever receives impulses
+    fn track_player(&mut self, world: &World) {
+        let l_trans  = world.read::<LocalTransform>();
+        let impulses = world.read::<ImpulseComponent>();
+
+        let position = (&l_trans, &impulses).iter()
+            .map(|(trans, _)| [trans.translation[0], trans.translation[1]])
+            .next();
+
+        if let (Some(last), Some(position)) = (self.last, position) {
+            let dx = position[0] - last[0];
+            let dy = position[1] - last[1];
+
+            self.distance += (dx * dx + dy * dy).sqrt();
+        }
+
+        self.last = position;
+    }
+
+    fn results(&self) -> HuntResults {
+        HuntResults {
+            time:     self.time,
+            distance: self.distance,
+            finished: self.time >= TIME_LIMIT,
+        }
+    }
+}
+
+impl State for HuntState {
+    fn on_start(&mut self, ctx: &mut Context, world: &mut World) {
+        self.camera = Some(super::create_camera(ctx, world));
+
+        self.spawn_level(ctx, world);
+        self.follow_player(world);
+    }
+
+    fn on_stop(&mut self, _ctx: &mut Context, world: &mut World) {
+        for entity in scene::entities(world).into_iter().chain(self.camera) {
+            world.delete_now(entity);
+        }
+
+        self.camera = None;
+    }
+
+    fn update(&mut self, ctx: &mut Context, world: &mut World) -> Trans {
+        // Automated runs have no one to show the results screen to
+        let automated = self.frames.is_some() || self.step.is_some();
+        let out_of_frames = self.frames.map_or(false, |f| self.frame >= f);
+
+        if out_of_frames || (automated && self.ended.get()) {
+            let results = self.results();
+
+            println!(
+                "Ran {} frames, {:.1}s, travelled {:.1}",
+                self.frame,
+                results.time,
+                results.distance
+            );
+
+            return Trans::Quit;
+        }
+
+        if self.ended.get() {
+            return Trans::Switch(Box::new(ResultsState::new(
+                self.level.clone(),
+                self.results(),
+            )));
+        }
+
+        // Processors run after states, so they step by this too
+        if let Some(step) = self.step {
+            ctx.delta_time = step;
+        }
+
+        self.frame += 1;
+        self.time  += super::delta_seconds(ctx);
+        self.track_player(world);
+        world.write_resource::<Replay>().step(ctx);
+
+        if self.time >= TIME_LIMIT {
+            self.ended.set(true);
+        }
+
+        let engine_events = ctx.broadcaster.read::<EngineEvent>();
+
+        let quicksave = self.level.quicksave.clone();
+
+        for engine_event in engine_events.iter() {
+            match engine_event.payload {
+                Event::Closed => return Trans::Quit,
+                Event::KeyboardInput(
+                    ElementState::Pressed,
+                    _,
+                    Some(VirtualKeyCode::Escape),
+                ) => return Trans::Push(Box::new(
+                    PauseState::new(self.ended.clone())
+                )),
+                Event::KeyboardInput(
+                    ElementState::Pressed,
+                    _,
+                    Some(VirtualKeyCode::F1),
+                ) => return Trans::Push(Box::new(
+                    EditorState::new(self.level.scene.clone(), PREFABS)
+                )),
+                Event::KeyboardInput(
+                    ElementState::Pressed,
+                    _,
+                    Some(VirtualKeyCode::F5),
+                ) => if let Err(e) = scene::save(world, &quicksave) {
+                    let _ = writeln!(io::stderr(), "Cannot save: {}", e);
+                },
+                Event::KeyboardInput(
+                    ElementState::Pressed,
+                    _,
+                    Some(VirtualKeyCode::F9),
+                ) => {
+                    let entities = scene::entities(world);
+
+                    match scene::load_file(world, &quicksave) {
+                        Ok(_) => {
+                            for entity in entities {
+                                world.delete_now(entity);
+                            }
+
+                            // Don't count the jump as distance travelled
+                            self.last = None;
+                            self.follow_player(world);
+                        },
+                        Err(e) => {
+                            let _ = writeln!(
+                                io::stderr(),
+                                "Cannot load {}: {}",
+                                quicksave.display(),
+                                e
+                            );
+                        },
+                    }
+                },
+                _ => (),
+            }
+        }
+
+        Trans::None
+    }
+}
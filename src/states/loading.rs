@@ -0,0 +1,164 @@
+use std::any::Any;
+use std::cell::Cell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use amethyst::context::Context;
+use amethyst::ecs::{Entity, World};
+use amethyst::engine::{State, Trans};
+use amethyst::processors::rendering::Renderable;
+use amethyst::processors::transform::{LocalTransform, Transform};
+
+use loaders::BackgroundLoader;
+use stores::AssetPaths;
+use systems::input::Mouse;
+use systems::physics::PhysicsPaused;
+use systems::viewport::Viewport;
+
+const BAR_WIDTH: f32 = 1.6;
+const WORKERS:   usize = 2;
+// Number of parsed assets handed to the GPU per frame
+const UPLOADS:   usize = 4;
+
+// Loads every file in the manifest on background threads while drawing a
+// progress bar, then switches to `next`.
+pub struct LoadingState {
+    paths:     AssetPaths,
+    // Added to the world on start, see `with_resource`
+    resources: Vec<Box<FnMut(&mut World)>>,
+    manifest:  Vec<String>,
+    loader:    Option<BackgroundLoader>,
+    next:      Option<Box<State>>,
+    // Set if anything fails to load, see `with_failure_flag`
+    failed:    Option<Rc<Cell<bool>>>,
+    entities:  Vec<Entity>,
+    bar:       Option<Entity>,
+}
+
+impl LoadingState {
+    pub fn new<S: State + 'static>(
+        paths: AssetPaths,
+        manifest: &[&str],
+        next: S,
+    ) -> Self {
+        LoadingState {
+            paths:     paths,
+            resources: vec![],
+            manifest:  manifest.iter().map(|s| s.to_string()).collect(),
+            loader:    None,
+            next:      Some(Box::new(next)),
+            failed:    None,
+            entities:  vec![],
+            bar:       None,
+        }
+    }
+
+    // So automated runs can tell a broken build from a good one once the game
+    // is over
+    pub fn with_failure_flag(mut self, failed: Rc<Cell<bool>>) -> Self {
+        self.failed = Some(failed);
+        self
+    }
+
+    // For resources that are made before the game starts, like settings
+    pub fn with_resource<R>(mut self, resource: R) -> Self
+        where R: Any + Send + Sync
+    {
+        let mut resource = Some(resource);
+
+        self.resources.push(Box::new(move |world: &mut World| {
+            if let Some(resource) = resource.take() {
+                world.add_resource(resource);
+            }
+        }));
+
+        self
+    }
+}
+
+impl State for LoadingState {
+    fn on_start(&mut self, ctx: &mut Context, world: &mut World) {
+        let mut loader = BackgroundLoader::new(self.paths.clone(), WORKERS);
+
+        for file in &self.manifest {
+            if let Err(e) = loader.queue_file(file) {
+                let _ = writeln!(io::stderr(), "Cannot queue {}: {}", file, e);
+            }
+        }
+
+        self.loader = Some(loader);
+
+        // This is the first state, so anything processors expect to find in
+        // the world goes in here
+        for add in &mut self.resources {
+            add(world);
+        }
+
+        world.add_resource(Mouse::default());
+        world.add_resource(PhysicsPaused(false));
+        world.add_resource(Viewport::default());
+
+        self.entities.push(super::create_camera(ctx, world));
+
+        ctx.asset_manager.create_constant_texture("loading_bar", [1.; 4]);
+        ctx.asset_manager.gen_rectangle("loading_bar", 1., 1.);
+
+        let mut l_trans = LocalTransform::default();
+        l_trans.scale   = [0., 0.05, 1.];
+
+        let bar = world.create_now()
+            .with(l_trans)
+            .with(Transform::default())
+            .with(Renderable::new("loading_bar", "loading_bar", "loading_bar"))
+            .build();
+
+        self.bar = Some(bar);
+        self.entities.push(bar);
+    }
+
+    fn on_stop(&mut self, _ctx: &mut Context, world: &mut World) {
+        for entity in self.entities.drain(..) {
+            world.delete_now(entity);
+        }
+    }
+
+    fn update(&mut self, ctx: &mut Context, world: &mut World) -> Trans {
+        let loader = match self.loader {
+            Some(ref mut loader) => loader,
+            None                 => return Trans::None,
+        };
+
+        loader.poll(&mut ctx.asset_manager, UPLOADS);
+
+        if let Some(bar) = self.bar {
+            let width = BAR_WIDTH * loader.progress();
+            let mut l_trans = world.write::<LocalTransform>();
+
+            if let Some(l_trans) = l_trans.get_mut(bar) {
+                l_trans.translation = [(width - BAR_WIDTH) / 2., 0., 0.];
+                l_trans.scale       = [width, 0.05, 1.];
+            }
+        }
+
+        if loader.is_done() {
+            if !loader.errors().is_empty() {
+                let _ = writeln!(
+                    io::stderr(),
+                    "{} of {} assets failed to load",
+                    loader.errors().len(),
+                    loader.total()
+                );
+
+                if let Some(ref failed) = self.failed {
+                    failed.set(true);
+                }
+            }
+
+            if let Some(next) = self.next.take() {
+                return Trans::Switch(next);
+            }
+        }
+
+        Trans::None
+    }
+}
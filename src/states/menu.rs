@@ -0,0 +1,219 @@
+use std::io::{self, Write};
+
+use amethyst::context::Context;
+use amethyst::context::event::{
+    ElementState,
+    EngineEvent,
+    Event,
+    VirtualKeyCode,
+};
+use amethyst::ecs::{Entity, World};
+use amethyst::engine::{State, Trans};
+use amethyst::processors::rendering::Renderable;
+use amethyst::processors::transform::LocalTransform;
+
+use settings::{DisplaySettings, Restart, MAX_BRIGHTNESS};
+use super::{HuntState, Level};
+
+const ITEM_SIZE: [f32; 2] = [0.8, 0.2];
+// Any darker and the menu is hard to find again
+const MIN_BRIGHTNESS:  f32 = 0.2;
+const BRIGHTNESS_STEP: f32 = 0.1;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Item {
+    Hunt,
+    // Shown as a bar as wide as the brightness, full width at the brightest
+    Brightness,
+    Fullscreen,
+    Quit,
+}
+
+const ITEMS: &'static [Item] = &[
+    Item::Hunt,
+    Item::Brightness,
+    Item::Fullscreen,
+    Item::Quit,
+];
+
+// The title screen. Up and down choose an item, enter confirms, and left and
+// right change the brightness. Settings are saved as soon as they change.
+pub struct MenuState {
+    level:    Level,
+    selected: usize,
+    // One per entry in `ITEMS`
+    items:    Vec<Entity>,
+    entities: Vec<Entity>,
+}
+
+impl MenuState {
+    pub fn new(level: Level) -> Self {
+        MenuState {
+            level:    level,
+            selected: 0,
+            items:    vec![],
+            entities: vec![],
+        }
+    }
+
+    fn highlight(&self, world: &mut World) {
+        let mut renderables = world.write::<Renderable>();
+
+        for (i, &item) in self.items.iter().enumerate() {
+            let texture = if i == self.selected {
+                "menu_selected"
+            } else {
+                "menu_item"
+            };
+
+            if let Some(rend) = renderables.get_mut(item) {
+                rend.ka = texture.into();
+                rend.kd = texture.into();
+            }
+        }
+    }
+
+    fn show_brightness(&self, world: &mut World) {
+        let brightness = world.read_resource::<DisplaySettings>().brightness;
+        let item       = ITEMS.iter()
+            .position(|&i| i == Item::Brightness)
+            .and_then(|i| self.items.get(i).cloned());
+
+        if let Some(item) = item {
+            let mut l_trans = world.write::<LocalTransform>();
+
+            if let Some(trans) = l_trans.get_mut(item) {
+                trans.scale[0] = ITEM_SIZE[0] * brightness / MAX_BRIGHTNESS;
+            }
+        }
+    }
+
+    fn change_brightness(&self, world: &mut World, by: f32) {
+        {
+            let mut settings = world.write_resource::<DisplaySettings>();
+
+            settings.brightness = (settings.brightness + by)
+                .max(MIN_BRIGHTNESS)
+                .min(MAX_BRIGHTNESS);
+            save(&settings);
+        }
+
+        self.show_brightness(world);
+    }
+
+    // Saves the setting and quits, for `main` to open a window with it
+    fn toggle_fullscreen(&self, world: &mut World) {
+        let mut settings = world.write_resource::<DisplaySettings>();
+
+        settings.fullscreen = !settings.fullscreen;
+        save(&settings);
+
+        world.read_resource::<Restart>().request();
+    }
+}
+
+fn save(settings: &DisplaySettings) {
+    if let Err(e) = settings.save() {
+        let _ = writeln!(io::stderr(), "Cannot save settings: {}", e);
+    }
+}
+
+impl State for MenuState {
+    fn on_start(&mut self, ctx: &mut Context, world: &mut World) {
+        self.entities.push(super::create_camera(ctx, world));
+
+        ctx.asset_manager.create_constant_texture("menu_item", [0.3; 4]);
+        ctx.asset_manager.create_constant_texture("menu_selected", [1.; 4]);
+
+        for i in 0..ITEMS.len() {
+            let y    = (i as f32 - (ITEMS.len() - 1) as f32 / 2.) * 0.3;
+            let item = super::create_panel(
+                ctx,
+                world,
+                "menu_item",
+                [0., y, 0.],
+                ITEM_SIZE,
+            );
+
+            self.items.push(item);
+        }
+
+        self.highlight(world);
+        self.show_brightness(world);
+    }
+
+    fn on_stop(&mut self, _ctx: &mut Context, world: &mut World) {
+        for entity in self.items.drain(..).chain(self.entities.drain(..)) {
+            world.delete_now(entity);
+        }
+    }
+
+    fn update(&mut self, ctx: &mut Context, world: &mut World) -> Trans {
+        let engine_events = ctx.broadcaster.read::<EngineEvent>();
+
+        for engine_event in engine_events.iter() {
+            match engine_event.payload {
+                Event::Closed |
+                Event::KeyboardInput(
+                    ElementState::Pressed,
+                    _,
+                    Some(VirtualKeyCode::Escape),
+                ) => return Trans::Quit,
+                Event::KeyboardInput(
+                    ElementState::Pressed,
+                    _,
+                    Some(VirtualKeyCode::Up),
+                ) => {
+                    self.selected =
+                        (self.selected + ITEMS.len() - 1) % ITEMS.len();
+                    self.highlight(world);
+                },
+                Event::KeyboardInput(
+                    ElementState::Pressed,
+                    _,
+                    Some(VirtualKeyCode::Down),
+                ) => {
+                    self.selected = (self.selected + 1) % ITEMS.len();
+                    self.highlight(world);
+                },
+                Event::KeyboardInput(
+                    ElementState::Pressed,
+                    _,
+                    Some(key @ VirtualKeyCode::Left),
+                ) |
+                Event::KeyboardInput(
+                    ElementState::Pressed,
+                    _,
+                    Some(key @ VirtualKeyCode::Right),
+                ) if ITEMS[self.selected] == Item::Brightness => {
+                    let by = if key == VirtualKeyCode::Left {
+                        -BRIGHTNESS_STEP
+                    } else {
+                        BRIGHTNESS_STEP
+                    };
+
+                    self.change_brightness(world, by);
+                },
+                Event::KeyboardInput(
+                    ElementState::Pressed,
+                    _,
+                    Some(VirtualKeyCode::Return),
+                ) => match ITEMS[self.selected] {
+                    Item::Hunt => return Trans::Switch(
+                        Box::new(HuntState::new(self.level.clone()))
+                    ),
+                    Item::Brightness => (),
+                    Item::Fullscreen => {
+                        self.toggle_fullscreen(world);
+
+                        return Trans::Quit;
+                    },
+                    Item::Quit => return Trans::Quit,
+                },
+                _ => (),
+            }
+        }
+
+        Trans::None
+    }
+}
@@ -0,0 +1,138 @@
+pub mod editor;
+pub mod hunt;
+pub mod loading;
+pub mod menu;
+pub mod pause;
+pub mod results;
+
+use amethyst::context::Context;
+use amethyst::ecs::{Entity, World};
+use amethyst::processors::rendering::{Camera, Renderable};
+use amethyst::processors::transform::{LocalTransform, Transform};
+
+use systems::physics::{quaternion_angle, PhysicsComponent};
+use systems::viewport::Viewport;
+
+pub use self::editor::EditorState;
+pub use self::hunt::{HuntState, Level};
+pub use self::loading::LoadingState;
+pub use self::menu::MenuState;
+pub use self::pause::PauseState;
+pub use self::results::{HuntResults, ResultsState};
+
+// Also creates the letterbox bars the first time it's called, which are kept
+// for as long as the game runs
+pub fn create_camera(ctx: &mut Context, world: &mut World) -> Entity {
+    // The Null backend has no window, so the viewport keeps the size it has,
+    // which starts out as the virtual resolution
+    let window = ctx.renderer.get_dimensions()
+        .map(|(w, h)| [w, h])
+        .unwrap_or_else(|| world.read_resource::<Viewport>().window());
+    let eye    = [0., 0., 0.1];
+    let target = [0., 0., 0.];
+    let up     = [0., 1., 0.];
+
+    let projection = {
+        let mut viewport = world.write_resource::<Viewport>();
+        viewport.resize(window);
+
+        viewport.projection()
+    };
+
+    world.add_resource(projection);
+
+    if world.read_resource::<Viewport>().bars.is_empty() {
+        create_overlays(ctx, world);
+    }
+
+    // Create a camera entity
+    let mut camera = Camera::new(projection, eye, target, up);
+    camera.activate();
+    world.create_now()
+        .with(camera)
+        .build()
+}
+
+// Placed by `ViewportProcessor`, which sets their `Transform` directly
+fn create_overlays(ctx: &mut Context, world: &mut World) {
+    ctx.asset_manager.create_constant_texture("letterbox", [0., 0., 0., 1.]);
+    ctx.asset_manager.gen_rectangle("panel", 1., 1.);
+
+    let bars = (0..4)
+        .map(|_| world.create_now()
+            .with(Transform::default())
+            .with(Renderable::new("panel", "letterbox", "letterbox"))
+            .build())
+        .collect();
+
+    world.write_resource::<Viewport>().bars = bars;
+}
+
+// A flat rectangle centred on `position`, for screens with nothing better to
+// draw. `texture` has to exist already, e.g. from `create_constant_texture`.
+pub fn create_panel(
+    ctx: &mut Context,
+    world: &mut World,
+    texture: &str,
+    position: [f32; 3],
+    size: [f32; 2],
+) -> Entity {
+    ctx.asset_manager.gen_rectangle("panel", 1., 1.);
+
+    let mut l_trans = LocalTransform::default();
+    l_trans.translation = position;
+    l_trans.scale       = [size[0], size[1], 1.];
+
+    world.create_now()
+        .with(l_trans)
+        .with(Transform::default())
+        .with(Renderable::new("panel", texture, texture))
+        .build()
+}
+
+// The last frame's length in seconds
+pub fn delta_seconds(ctx: &Context) -> f32 {
+    let dt = ctx.delta_time;
+
+    dt.as_secs() as f32 + dt.subsec_nanos() as f32 * 1.0e-9
+}
+
+// Where a point in the window (in pixels from the top left) is in the world,
+// as seen by the first camera
+pub fn screen_to_world(world: &World, pos: [f32; 2]) -> Option<[f32; 2]> {
+    use amethyst::ecs::Join;
+
+    let cameras = world.read::<Camera>();
+    let eye     = match (&cameras).iter().next() {
+        Some(camera) => camera.eye,
+        None         => return None,
+    };
+
+    let viewport = world.read_resource::<Viewport>();
+
+    Some(viewport.screen_to_world([eye[0], eye[1]], pos))
+}
+
+// The entity whose physics body is under `pos` (in pixels from the top left of
+// the window), by a point query against each body's collider. `Mouse::hovered`
+// already has this for the cursor.
+pub fn pick(world: &World, pos: [f32; 2]) -> Option<Entity> {
+    use amethyst::ecs::Join;
+
+    let point = match screen_to_world(world, pos) {
+        Some(point) => point,
+        None        => return None,
+    };
+
+    let entities = world.entities();
+    let l_trans  = world.read::<LocalTransform>();
+    let physics  = world.read::<PhysicsComponent>();
+
+    (&entities, &l_trans, &physics).iter()
+        .find(|&(_, trans, phys)| phys.desc().map_or(false, |desc| {
+            let position = [trans.translation[0], trans.translation[1]];
+
+            desc.contains(position, quaternion_angle(trans.rotation), point)
+        }))
+        .map(|(entity, _, _)| entity)
+}
@@ -0,0 +1,97 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use amethyst::context::Context;
+use amethyst::context::event::{
+    ElementState,
+    EngineEvent,
+    Event,
+    VirtualKeyCode,
+};
+use amethyst::ecs::{Entity, World};
+use amethyst::engine::{State, Trans};
+
+use replay::Replay;
+use systems::physics::PhysicsPaused;
+
+// Pushed over a hunt, which stops simulating until this is popped. Escape
+// resumes, Q gives up and goes to the results.
+pub struct PauseState {
+    end:      Rc<Cell<bool>>,
+    entities: Vec<Entity>,
+    // Whether physics was paused before this was pushed
+    paused:   bool,
+}
+
+impl PauseState {
+    pub fn new(end: Rc<Cell<bool>>) -> Self {
+        PauseState {
+            end:      end,
+            entities: vec![],
+            paused:   false,
+        }
+    }
+}
+
+impl State for PauseState {
+    fn on_start(&mut self, ctx: &mut Context, world: &mut World) {
+        {
+            let mut paused = world.write_resource::<PhysicsPaused>();
+
+            self.paused = paused.0;
+            paused.0    = true;
+        }
+
+        ctx.asset_manager.create_constant_texture("pause", [1.; 4]);
+
+        // Drawn in front of the level, which is at z = 0
+        for &x in &[-0.15, 0.15] {
+            let bar = super::create_panel(
+                ctx,
+                world,
+                "pause",
+                [x, 0., 0.05],
+                [0.15, 0.6],
+            );
+
+            self.entities.push(bar);
+        }
+    }
+
+    fn on_stop(&mut self, _ctx: &mut Context, world: &mut World) {
+        world.write_resource::<PhysicsPaused>().0 = self.paused;
+
+        for entity in self.entities.drain(..) {
+            world.delete_now(entity);
+        }
+    }
+
+    fn update(&mut self, ctx: &mut Context, world: &mut World) -> Trans {
+        world.write_resource::<Replay>().step(ctx);
+
+        let engine_events = ctx.broadcaster.read::<EngineEvent>();
+
+        for engine_event in engine_events.iter() {
+            match engine_event.payload {
+                Event::Closed => return Trans::Quit,
+                Event::KeyboardInput(
+                    ElementState::Pressed,
+                    _,
+                    Some(VirtualKeyCode::Escape),
+                ) => return Trans::Pop,
+                Event::KeyboardInput(
+                    ElementState::Pressed,
+                    _,
+                    Some(VirtualKeyCode::Q),
+                ) => {
+                    self.end.set(true);
+
+                    return Trans::Pop;
+                },
+                _ => (),
+            }
+        }
+
+        Trans::None
+    }
+}
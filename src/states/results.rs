@@ -0,0 +1,116 @@
+use std::io::{self, Write};
+
+use amethyst::context::Context;
+use amethyst::context::event::{
+    ElementState,
+    EngineEvent,
+    Event,
+    VirtualKeyCode,
+};
+use amethyst::ecs::{Entity, World};
+use amethyst::engine::{State, Trans};
+
+use super::{Level, MenuState};
+use super::hunt::TIME_LIMIT;
+
+const BAR_WIDTH: f32 = 1.6;
+// Distance covered by a full bar
+const DISTANCE_BAR: f32 = 50.;
+
+#[derive(Clone, Copy, Debug)]
+pub struct HuntResults {
+    // In seconds
+    pub time:     f32,
+    pub distance: f32,
+    // Whether the hunt ran out of time rather than being given up on
+    pub finished: bool,
+}
+
+// Shown after a hunt, as one bar for the time taken and one for the distance
+// travelled. Enter or escape go back to the menu.
+pub struct ResultsState {
+    level:    Level,
+    results:  HuntResults,
+    entities: Vec<Entity>,
+}
+
+impl ResultsState {
+    pub fn new(level: Level, results: HuntResults) -> Self {
+        ResultsState {
+            level:    level,
+            results:  results,
+            entities: vec![],
+        }
+    }
+}
+
+impl State for ResultsState {
+    fn on_start(&mut self, ctx: &mut Context, world: &mut World) {
+        let results = self.results;
+
+        let _ = writeln!(
+            io::stderr(),
+            "Hunt {} after {:.1}s, travelled {:.1}",
+            if results.finished { "over" } else { "abandoned" },
+            results.time,
+            results.distance
+        );
+
+        self.entities.push(super::create_camera(ctx, world));
+
+        ctx.asset_manager.create_constant_texture("results_time", [1.; 4]);
+        ctx.asset_manager.create_constant_texture(
+            "results_distance",
+            [0.4, 0.8, 0.4, 1.],
+        );
+
+        let bars = [
+            ("results_time", results.time / TIME_LIMIT, -0.15),
+            ("results_distance", results.distance / DISTANCE_BAR, 0.15),
+        ];
+
+        for &(texture, fraction, y) in &bars {
+            let width = BAR_WIDTH * fraction.max(0.).min(1.);
+            let bar   = super::create_panel(
+                ctx,
+                world,
+                texture,
+                [(width - BAR_WIDTH) / 2., y, 0.],
+                [width, 0.1],
+            );
+
+            self.entities.push(bar);
+        }
+    }
+
+    fn on_stop(&mut self, _ctx: &mut Context, world: &mut World) {
+        for entity in self.entities.drain(..) {
+            world.delete_now(entity);
+        }
+    }
+
+    fn update(&mut self, ctx: &mut Context, _world: &mut World) -> Trans {
+        let engine_events = ctx.broadcaster.read::<EngineEvent>();
+
+        for engine_event in engine_events.iter() {
+            match engine_event.payload {
+                Event::Closed => return Trans::Quit,
+                Event::KeyboardInput(
+                    ElementState::Pressed,
+                    _,
+                    Some(VirtualKeyCode::Return),
+                ) |
+                Event::KeyboardInput(
+                    ElementState::Pressed,
+                    _,
+                    Some(VirtualKeyCode::Escape),
+                ) => return Trans::Switch(
+                    Box::new(MenuState::new(self.level.clone()))
+                ),
+                _ => (),
+            }
+        }
+
+        Trans::None
+    }
+}
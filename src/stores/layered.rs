@@ -0,0 +1,47 @@
+use amethyst::context::asset_manager::AssetStore;
+
+// Stacks several stores on top of each other. Layers pushed later win, so base
+// assets go in first and mods/DLC directories after them.
+pub struct LayeredStore {
+    layers: Vec<Box<AssetStore>>,
+}
+
+impl LayeredStore {
+    pub fn new() -> Self {
+        LayeredStore {
+            layers: vec![],
+        }
+    }
+
+    pub fn with<S: AssetStore + 'static>(mut self, store: S) -> Self {
+        self.push(store);
+        self
+    }
+
+    pub fn push<S: AssetStore + 'static>(&mut self, store: S) {
+        self.layers.push(Box::new(store));
+    }
+
+    fn find(&self, name: &str, asset_type: &str) -> Option<&AssetStore> {
+        self.layers.iter()
+            .rev()
+            .find(|s| s.has_asset(name, asset_type))
+            .map(|s| &**s)
+    }
+}
+
+impl AssetStore for LayeredStore {
+    fn has_asset(&self, name: &str, asset_type: &str) -> bool {
+        self.find(name, asset_type).is_some()
+    }
+
+    fn load_asset(
+        &self,
+        name: &str,
+        asset_type: &str,
+        buf: &mut Vec<u8>,
+    ) -> Option<usize> {
+        self.find(name, asset_type)
+            .and_then(|s| s.load_asset(name, asset_type, buf))
+    }
+}
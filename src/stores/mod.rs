@@ -0,0 +1,7 @@
+pub mod layered;
+pub mod pack;
+pub mod root;
+
+pub use self::layered::LayeredStore;
+pub use self::pack::PackStore;
+pub use self::root::AssetPaths;
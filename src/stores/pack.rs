@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use amethyst::context::asset_manager::AssetStore;
+
+// Layout of a pack file, all integers little-endian:
+//
+//     b"HGPK"  u32 version  u32 entry count
+//     entry*:  u16 name length, name (UTF-8, `/`-separated), u64 offset,
+//              u64 length
+//     data
+//
+// Offsets are from the start of the file.
+pub const MAGIC:   &'static [u8; 4] = b"HGPK";
+pub const VERSION: u32              = 1;
+// Bytes in an entry with an empty name
+const ENTRY_MIN:   u64              = 2 + 8 + 8;
+
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+    offset: u64,
+    length: u64,
+}
+
+pub struct PackStore {
+    file:  Mutex<File>,
+    index: HashMap<String, Entry>,
+}
+
+impl PackStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+
+        let mut magic = [0; 4];
+        file.read_exact(&mut magic)?;
+
+        if &magic != MAGIC {
+            return Err(invalid("not an asset pack"));
+        }
+
+        if read_u32(&mut file)? != VERSION {
+            return Err(invalid("unsupported pack version"));
+        }
+
+        let size  = file.metadata()?.len();
+        let count = read_u32(&mut file)?;
+
+        // Checked before allocating, since every entry takes at least this
+        // many bytes of header
+        if count as u64 * ENTRY_MIN > size {
+            return Err(invalid("entry count is larger than the pack"));
+        }
+
+        let mut index = HashMap::with_capacity(count as usize);
+
+        for _ in 0..count {
+            let mut name = vec![0; read_u16(&mut file)? as usize];
+            file.read_exact(&mut name)?;
+
+            let name = String::from_utf8(name)
+                .map_err(|_| invalid("entry name is not UTF-8"))?;
+
+            let entry = Entry {
+                offset: read_u64(&mut file)?,
+                length: read_u64(&mut file)?,
+            };
+
+            let in_bounds = entry.offset.checked_add(entry.length)
+                .map_or(false, |end| end <= size);
+
+            if !in_bounds {
+                return Err(invalid("entry runs past the end of the pack"));
+            }
+
+            index.insert(name, entry);
+        }
+
+        Ok(PackStore {
+            file:  Mutex::new(file),
+            index: index,
+        })
+    }
+}
+
+impl AssetStore for PackStore {
+    fn has_asset(&self, name: &str, asset_type: &str) -> bool {
+        self.index.contains_key(&format!("{}.{}", name, asset_type))
+    }
+
+    fn load_asset(
+        &self,
+        name: &str,
+        asset_type: &str,
+        buf: &mut Vec<u8>,
+    ) -> Option<usize> {
+        let entry = match self.index.get(&format!("{}.{}", name, asset_type)) {
+            Some(entry) => *entry,
+            None        => return None,
+        };
+
+        let mut file = self.file.lock().unwrap();
+
+        if file.seek(SeekFrom::Start(entry.offset)).is_err() {
+            return None;
+        }
+
+        let start = buf.len();
+        buf.resize(start + entry.length as usize, 0);
+
+        match file.read_exact(&mut buf[start..]) {
+            Ok(()) => Some(entry.length as usize),
+            Err(_) => {
+                buf.truncate(start);
+                None
+            },
+        }
+    }
+}
+
+// Packs every file under `dir` (recursively) into `out`, returning the number
+// of files written.
+pub fn write_pack<P: AsRef<Path>, W: Write>(
+    dir: P,
+    out: &mut W,
+) -> io::Result<usize> {
+    let mut files = vec![];
+    collect_files(dir.as_ref(), dir.as_ref(), &mut files)?;
+    files.sort();
+
+    // Checked up front so nothing is written for a pack that can't be read
+    if files.len() as u64 > ::std::u32::MAX as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "too many files for one pack",
+        ));
+    }
+
+    for &(ref name, _) in &files {
+        if name.len() > ::std::u16::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is too long a name to pack", name),
+            ));
+        }
+    }
+
+    let header_len = 4 + 4 + 4 + files.iter()
+        .map(|&(ref name, _)| 2 + name.len() as u64 + 8 + 8)
+        .sum::<u64>();
+
+    out.write_all(MAGIC)?;
+    write_u32(out, VERSION)?;
+    write_u32(out, files.len() as u32)?;
+
+    let mut offset = header_len;
+
+    for &(ref name, ref path) in &files {
+        let length = fs::metadata(path)?.len();
+
+        write_u16(out, name.len() as u16)?;
+        out.write_all(name.as_bytes())?;
+        write_u64(out, offset)?;
+        write_u64(out, length)?;
+
+        offset += length;
+    }
+
+    for &(_, ref path) in &files {
+        io::copy(&mut File::open(path)?, out)?;
+    }
+
+    Ok(files.len())
+}
+
+fn collect_files(
+    base: &Path,
+    dir: &Path,
+    out: &mut Vec<(String, ::std::path::PathBuf)>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            collect_files(base, &path, out)?;
+        } else if let Ok(rel) = path.strip_prefix(base) {
+            let name = rel.components()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            out.push((name, path.clone()));
+        }
+    }
+
+    Ok(())
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+macro_rules! le_io {
+    ($read:ident, $write:ident, $t:ty, $n:expr) => {
+        fn $read<R: Read>(r: &mut R) -> io::Result<$t> {
+            let mut bytes = [0u8; $n];
+            r.read_exact(&mut bytes)?;
+
+            Ok(bytes.iter().rev().fold(0, |acc, &b| (acc << 8) | b as $t))
+        }
+
+        fn $write<W: Write>(w: &mut W, value: $t) -> io::Result<()> {
+            let mut bytes = [0u8; $n];
+
+            for (i, b) in bytes.iter_mut().enumerate() {
+                *b = (value >> (i * 8)) as u8;
+            }
+
+            w.write_all(&bytes)
+        }
+    }
+}
+
+le_io!(read_u16, write_u16, u16, 2);
+le_io!(read_u32, write_u32, u32, 4);
+le_io!(read_u64, write_u64, u64, 8);
@@ -0,0 +1,158 @@
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use amethyst::context::asset_manager::DirectoryStore;
+use yaml_rust::Yaml;
+
+use cli::Options;
+use loaders::yaml;
+use super::{LayeredStore, PackStore};
+
+pub const ASSETS_ENV: &'static str = "HUNTING_GAME_ASSETS";
+
+// Where assets are read from. The root is resolved, in order of preference,
+// from `--assets <dir>`, `$HUNTING_GAME_ASSETS`, `assets.root` in config.yml
+// (or the file given with `--config`) and finally the `resources/assets`
+// directory next to the executable. Packs (`--pack <file>`, `assets.packs`, or
+// `resources/assets.pack` if present) sit underneath the root, so loose files
+// always override packed ones.
+#[derive(Clone, Debug)]
+pub struct AssetPaths {
+    pub resources: PathBuf,
+    pub config:    PathBuf,
+    pub packs:     Vec<PathBuf>,
+    pub root:      PathBuf,
+    // Lowest priority first
+    pub overlays:  Vec<PathBuf>,
+}
+
+impl AssetPaths {
+    pub fn discover(options: &Options) -> Self {
+        let resources   = resources_dir();
+        let config_path = options.config.clone()
+            .unwrap_or_else(|| resources.join("config.yml"));
+        let config      = yaml::read_file(&config_path)
+            .unwrap_or(Yaml::Null);
+
+        let root = options.assets.clone()
+            .or_else(|| env::var_os(ASSETS_ENV).map(PathBuf::from))
+            .or_else(
+                || config["assets"]["root"].as_str()
+                    .map(|r| resources.join(r))
+            )
+            .unwrap_or_else(|| resources.join("assets"));
+
+        let mut packs = vec![];
+
+        if resources.join("assets.pack").is_file() {
+            packs.push(resources.join("assets.pack"));
+        }
+
+        if let Some(list) = config["assets"]["packs"].as_vec() {
+            packs.extend(
+                list.iter()
+                    .filter_map(Yaml::as_str)
+                    .map(|p| resources.join(p))
+            );
+        }
+
+        packs.extend(options.packs.iter().cloned());
+
+        let mut overlays = mods(&resources.join("mods"));
+
+        if let Some(list) = config["assets"]["overlays"].as_vec() {
+            overlays.extend(
+                list.iter()
+                    .filter_map(Yaml::as_str)
+                    .map(|o| resources.join(o))
+            );
+        }
+
+        overlays.extend(options.overlays.iter().cloned());
+
+        AssetPaths {
+            resources: resources,
+            config:    config_path,
+            packs:     packs,
+            root:      root,
+            overlays:  overlays,
+        }
+    }
+
+    // Every directory assets are read from, highest priority first
+    pub fn layers(&self) -> Vec<PathBuf> {
+        self.overlays.iter()
+            .rev()
+            .chain(Some(&self.root))
+            .cloned()
+            .collect()
+    }
+
+    pub fn store(&self) -> LayeredStore {
+        let mut store = LayeredStore::new();
+
+        for pack in &self.packs {
+            match PackStore::open(pack) {
+                Ok(pack) => store.push(pack),
+                Err(e)   => {
+                    let _ = writeln!(
+                        io::stderr(),
+                        "Cannot open asset pack {}: {}",
+                        pack.display(),
+                        e
+                    );
+                },
+            }
+        }
+
+        for dir in Some(&self.root).into_iter().chain(&self.overlays) {
+            store.push(DirectoryStore::new(dir));
+        }
+
+        store
+    }
+}
+
+fn resources_dir() -> PathBuf {
+    let mut candidates = vec![];
+
+    if let Some(exe_dir) = env::current_exe().ok()
+        .and_then(|p| p.parent().map(Path::to_path_buf))
+    {
+        candidates.push(exe_dir.join("resources"));
+    }
+
+    if let Ok(cwd) = env::current_dir() {
+        candidates.push(cwd.join("resources"));
+    }
+
+    // Lets `cargo run` work from anywhere during development, without the
+    // developer's path leaking into release builds.
+    if cfg!(debug_assertions) {
+        candidates.push(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("resources")
+        );
+    }
+
+    candidates.iter()
+        .find(|c| c.is_dir())
+        .or(candidates.first())
+        .cloned()
+        .unwrap_or_else(|| PathBuf::from("resources"))
+}
+
+// Every subdirectory of `resources/mods`, in alphabetical order so that load
+// order is predictable.
+fn mods(dir: &Path) -> Vec<PathBuf> {
+    let mut out = fs::read_dir(dir).ok()
+        .map(|entries| entries.filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_dir())
+            .collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    out.sort();
+    out
+}
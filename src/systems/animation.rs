@@ -0,0 +1,142 @@
+use std::sync::{Arc, Mutex};
+
+use amethyst::context::Context;
+use amethyst::ecs::{
+    RunArg,
+    Processor,
+    Component,
+    Entity,
+    VecStorage,
+};
+use amethyst::processors::rendering::Renderable;
+
+use loaders::{PlayMode, SpriteSheet};
+
+pub struct SpriteAnimation {
+    sheet:    SpriteSheet,
+    clip:     String,
+    time:     f32,
+    finished: bool,
+}
+
+impl SpriteAnimation {
+    pub fn new(sheet: SpriteSheet, clip: &str) -> Self {
+        SpriteAnimation {
+            sheet:    sheet,
+            clip:     clip.into(),
+            time:     0.,
+            finished: false,
+        }
+    }
+
+    pub fn clip(&self) -> &str {
+        &self.clip
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    // Switching to the clip that's already playing is a no-op, so this can be
+    // called every frame from gameplay code.
+    pub fn play(&mut self, clip: &str) {
+        if self.clip != clip {
+            self.restart(clip);
+        }
+    }
+
+    pub fn restart(&mut self, clip: &str) {
+        self.clip     = clip.into();
+        self.time     = 0.;
+        self.finished = false;
+    }
+
+    fn frame(&self) -> Option<usize> {
+        let clip = match self.sheet.clips.get(&self.clip) {
+            Some(clip) => clip,
+            None       => return None,
+        };
+        let index = (self.time / clip.frame_time) as usize;
+
+        let index = match clip.mode {
+            PlayMode::Loop => index % clip.frames.len(),
+            PlayMode::Once => ::std::cmp::min(index, clip.frames.len() - 1),
+        };
+
+        Some(clip.frames[index])
+    }
+}
+
+impl Component for SpriteAnimation {
+    type Storage = VecStorage<SpriteAnimation>;
+}
+
+// Published through the context's broadcaster whenever a clip ends, i.e. once
+// its last frame has been shown for a full frame time. Looping clips fire once
+// per loop.
+#[derive(Clone, Debug)]
+pub struct AnimationEvent {
+    pub entity: Entity,
+    pub clip:   String,
+}
+
+impl Component for AnimationEvent {
+    type Storage = VecStorage<AnimationEvent>;
+}
+
+pub struct AnimationProcessor;
+
+impl Processor<Arc<Mutex<Context>>> for AnimationProcessor {
+    fn run(&mut self, arg: RunArg, context: Arc<Mutex<Context>>) {
+        use amethyst::ecs::Join;
+
+        let (entities, mut l_anims, mut l_rends) = arg.fetch(
+            |w| (
+                w.entities(),
+                w.write::<SpriteAnimation>(),
+                w.write::<Renderable>(),
+            )
+        );
+        let mut context = context.lock().unwrap();
+
+        let dt = context.delta_time;
+        let dt_secs =
+            dt.as_secs() as f32 +
+            (dt.subsec_nanos() as f32 * 1.0e-9);
+
+        for (entity, anim, rend) in
+            (&entities, &mut l_anims, &mut l_rends).iter()
+        {
+            if anim.finished {
+                continue;
+            }
+
+            let (duration, mode) = match anim.sheet.clips.get(&anim.clip) {
+                Some(clip) => (clip.duration(), clip.mode),
+                None       => continue,
+            };
+
+            let before = anim.time;
+            anim.time += dt_secs;
+
+            if (before / duration).floor() != (anim.time / duration).floor() {
+                if mode == PlayMode::Once {
+                    anim.finished = true;
+                } else {
+                    anim.time %= duration;
+                }
+
+                context.broadcaster.publish()
+                    .with(AnimationEvent {
+                        entity: entity,
+                        clip:   anim.clip.clone(),
+                    })
+                    .build();
+            }
+
+            if let Some(frame) = anim.frame() {
+                rend.mesh = anim.sheet.frame_mesh(frame);
+            }
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use gfx;
+use gfx::traits::FactoryExt;
+use amethyst::context::Context;
+use amethyst::context::asset_manager::FactoryImpl;
+use amethyst::ecs::{
+    RunArg,
+    Processor,
+};
+use amethyst::renderer::{
+    ConstantColorTexture,
+    Pass,
+    PassDescription,
+    Pipeline,
+    Scene,
+    VertexPosNormal,
+};
+use amethyst::renderer::pass;
+use amethyst::renderer::target::ColorBuffer;
+
+use settings::DisplaySettings;
+
+static VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    layout (std140) uniform cb_VertexArgs {
+        uniform mat4 u_Proj;
+        uniform mat4 u_View;
+        uniform mat4 u_Model;
+    };
+
+    in vec3 a_Pos;
+    in vec3 a_Normal;
+    in vec2 a_TexCoord;
+
+    out vec2 v_TexCoord;
+
+    void main() {
+        v_TexCoord = a_TexCoord;
+        gl_Position = u_Proj * u_View * u_Model * vec4(a_Pos, 1.0);
+    }
+";
+
+static FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform sampler2D t_Ka;
+    uniform float f_Brightness;
+
+    in vec2 v_TexCoord;
+
+    out vec4 o_Color;
+
+    void main() {
+        vec4 ka = texture(t_Ka, v_TexCoord);
+
+        o_Color = vec4(ka.rgb * f_Brightness, ka.a);
+    }
+";
+
+gfx_defines! {
+    constant VertexArgs {
+        proj:  [[f32; 4]; 4] = "u_Proj",
+        view:  [[f32; 4]; 4] = "u_View",
+        model: [[f32; 4]; 4] = "u_Model",
+    }
+
+    pipeline bright {
+        vbuf:        gfx::VertexBuffer<VertexPosNormal> = (),
+        vertex_args: gfx::ConstantBuffer<VertexArgs> = "cb_VertexArgs",
+        out_ka:      gfx::RenderTarget<gfx::format::Rgba8> = "o_Color",
+        out_depth:   gfx::DepthTarget<gfx::format::DepthStencil> =
+            gfx::preset::depth::LESS_EQUAL_WRITE,
+        ka:          gfx::TextureSampler<[f32; 4]> = "t_Ka",
+        brightness:  gfx::Global<f32> = "f_Brightness",
+    }
+}
+
+// The display brightness as the renderer sees it. Shared between the pass,
+// which lives in the renderer, and `BrightnessProcessor`, which keeps it in
+// step with `DisplaySettings`.
+#[derive(Clone, Debug)]
+pub struct Brightness(Arc<AtomicUsize>);
+
+impl Brightness {
+    pub fn new(brightness: f32) -> Self {
+        Brightness(Arc::new(AtomicUsize::new(brightness.to_bits() as usize)))
+    }
+
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed) as u32)
+    }
+
+    pub fn set(&self, brightness: f32) {
+        self.0.store(brightness.to_bits() as usize, Ordering::Relaxed);
+    }
+}
+
+// Draws the scene like the renderer's `DrawFlat`, with every colour multiplied
+// by the brightness in the fragment shader
+#[derive(Clone, Debug)]
+pub struct DrawBright {
+    pub camera: String,
+    pub scene:  String,
+}
+
+impl PassDescription for DrawBright {}
+
+pub struct DrawBrightPass<R: gfx::Resources> {
+    brightness: Brightness,
+    vertex:     gfx::handle::Buffer<R, VertexArgs>,
+    ka:         ConstantColorTexture<R>,
+    pso:        gfx::pso::PipelineState<R, bright::Meta>,
+    sampler:    gfx::handle::Sampler<R>,
+}
+
+impl<R: gfx::Resources> DrawBrightPass<R> {
+    pub fn new<F>(factory: &mut F, brightness: Brightness) -> Self
+        where F: gfx::Factory<R>
+    {
+        let pso = factory.create_pipeline_simple(
+            VERTEX_SRC,
+            FRAGMENT_SRC,
+            bright::new(),
+        ).unwrap();
+
+        let sampler = factory.create_sampler(gfx::tex::SamplerInfo::new(
+            gfx::tex::FilterMethod::Scale,
+            gfx::tex::WrapMode::Clamp,
+        ));
+
+        DrawBrightPass {
+            brightness: brightness,
+            vertex:     factory.create_constant_buffer(1),
+            ka:         ConstantColorTexture::new(factory),
+            pso:        pso,
+            sampler:    sampler,
+        }
+    }
+}
+
+impl<R: gfx::Resources> Pass<R> for DrawBrightPass<R> {
+    type Arg    = DrawBright;
+    type Target = ColorBuffer<R>;
+
+    fn apply<C>(
+        &self,
+        _: &DrawBright,
+        target: &ColorBuffer<R>,
+        _: &Pipeline,
+        scene: &Scene<R>,
+        encoder: &mut gfx::Encoder<R, C>,
+    )
+        where C: gfx::CommandBuffer<R>
+    {
+        let brightness = self.brightness.get();
+
+        for fragment in &scene.fragments {
+            encoder.update_constant_buffer(&self.vertex, &VertexArgs {
+                proj:  scene.camera.projection,
+                view:  scene.camera.view,
+                model: fragment.transform,
+            });
+
+            let ka = fragment.ka.to_view(&self.ka, encoder);
+
+            encoder.draw(&fragment.slice, &self.pso, &bright::Data {
+                vbuf:        fragment.buffer.clone(),
+                vertex_args: self.vertex.clone(),
+                out_ka:      target.color.clone(),
+                out_depth:   target.output_depth.clone(),
+                ka:          (ka, self.sampler.clone()),
+                brightness:  brightness,
+            });
+        }
+    }
+}
+
+// Adds the pass to the renderer and draws with it instead of `DrawFlat` and
+// `DrawShaded`, which nothing here lights anyway. Call after the rendering
+// processor has set up the pipeline.
+pub fn install(context: &mut Context, brightness: Brightness) {
+    let draw = match context.asset_manager.get_loader_mut::<FactoryImpl>() {
+        Some(&mut FactoryImpl::OpenGL { ref mut factory }) =>
+            DrawBrightPass::new(factory, brightness),
+        // No window to draw to
+        _ => return,
+    };
+
+    context.renderer.add_pass(draw);
+
+    for layer in &mut context.renderer.pipeline.layers {
+        for desc in &mut layer.passes {
+            let replace = match (
+                desc.downcast_ref::<pass::DrawFlat>(),
+                desc.downcast_ref::<pass::DrawShaded>(),
+            ) {
+                (Some(flat), _) =>
+                    Some((flat.camera.clone(), flat.scene.clone())),
+                (_, Some(shaded)) =>
+                    Some((shaded.camera.clone(), shaded.scene.clone())),
+                _ => None,
+            };
+
+            if let Some((camera, scene)) = replace {
+                *desc = Box::new(DrawBright {
+                    camera: camera,
+                    scene:  scene,
+                });
+            }
+        }
+    }
+}
+
+// Hands the brightness from `DisplaySettings` to the pass every frame, so the
+// menu only has to change the setting
+pub struct BrightnessProcessor(pub Brightness);
+
+impl Processor<Arc<Mutex<Context>>> for BrightnessProcessor {
+    fn run(&mut self, arg: RunArg, _context: Arc<Mutex<Context>>) {
+        let brightness =
+            arg.fetch(|w| w.read_resource::<DisplaySettings>().brightness);
+
+        self.0.set(brightness);
+    }
+}
@@ -0,0 +1,144 @@
+use std::sync::{Arc, Mutex};
+
+use amethyst::context::Context;
+use amethyst::ecs::{
+    RunArg,
+    Processor,
+    Component,
+    Entity,
+    VecStorage,
+};
+use amethyst::processors::rendering::Camera;
+use amethyst::processors::transform::LocalTransform;
+
+use states::delta_seconds;
+use super::viewport::Viewport;
+
+// Added to a camera entity to have it follow `target` around. The target is
+// expected to be a root entity, since only its local transform is read.
+#[derive(Clone, Debug)]
+pub struct CameraController {
+    pub target:     Entity,
+    // How quickly the camera catches up, per second. Zero never moves, and
+    // anything above ~30 is hard to tell apart from snapping.
+    pub smoothing:  f32,
+    // Half the size of the box around the centre of the view that the target
+    // can move around in without the camera following
+    pub dead_zone:  [f32; 2],
+    // Seconds of the target's velocity to look ahead by
+    pub look_ahead: f32,
+    // Smallest and largest corner the view has to stay inside
+    pub bounds:     Option<([f32; 2], [f32; 2])>,
+    focus:          Option<[f32; 2]>,
+    last:           Option<[f32; 2]>,
+    lead:           [f32; 2],
+}
+
+impl CameraController {
+    pub fn new(target: Entity) -> Self {
+        CameraController {
+            target:     target,
+            smoothing:  5.,
+            dead_zone:  [0.2, 0.2],
+            look_ahead: 0.3,
+            bounds:     None,
+            focus:      None,
+            last:       None,
+            lead:       [0.; 2],
+        }
+    }
+
+    pub fn with_bounds(mut self, min: [f32; 2], max: [f32; 2]) -> Self {
+        self.bounds = Some((min, max));
+        self
+    }
+
+    // Moves the focus towards `position`, where `half_view` is half the width
+    // and height of what the camera can see (see `Viewport::half_view`)
+    fn follow(&mut self, position: [f32; 2], half_view: [f32; 2], dt: f32) {
+        let mut focus = match self.focus {
+            Some(focus) => focus,
+            // Start out on the target rather than sweeping over to it
+            None        => position,
+        };
+
+        // Exponential, so it behaves the same whatever the frame rate
+        let t = 1. - (-self.smoothing * dt).exp();
+
+        for axis in 0..2 {
+            if let Some(last) = self.last {
+                let velocity = if dt > 0. {
+                    (position[axis] - last[axis]) / dt
+                } else {
+                    0.
+                };
+
+                let lead = velocity * self.look_ahead;
+                self.lead[axis] += (lead - self.lead[axis]) * t;
+            }
+
+            let offset = position[axis] + self.lead[axis] - focus[axis];
+            let slack  = offset.abs() - self.dead_zone[axis];
+
+            if slack > 0. {
+                focus[axis] += slack * offset.signum() * t;
+            }
+
+            if let Some((min, max)) = self.bounds {
+                let (low, high) = (
+                    min[axis] + half_view[axis],
+                    max[axis] - half_view[axis],
+                );
+
+                // Levels smaller than the view are centred instead
+                focus[axis] = if low > high {
+                    (min[axis] + max[axis]) / 2.
+                } else {
+                    focus[axis].max(low).min(high)
+                };
+            }
+        }
+
+        self.focus = Some(focus);
+        self.last  = Some(position);
+    }
+}
+
+impl Component for CameraController {
+    type Storage = VecStorage<CameraController>;
+}
+
+pub struct CameraProcessor;
+
+impl Processor<Arc<Mutex<Context>>> for CameraProcessor {
+    fn run(&mut self, arg: RunArg, context: Arc<Mutex<Context>>) {
+        use amethyst::ecs::Join;
+
+        let (mut l_cameras, mut l_controllers, l_trans, half_view) = arg.fetch(
+            |w| (
+                w.write::<Camera>(),
+                w.write::<CameraController>(),
+                w.read::<LocalTransform>(),
+                w.read_resource::<Viewport>().half_view(),
+            )
+        );
+
+        let dt = delta_seconds(&context.lock().unwrap());
+
+        for (camera, controller) in
+            (&mut l_cameras, &mut l_controllers).iter()
+        {
+            let position = match l_trans.get(controller.target) {
+                Some(trans) => [trans.translation[0], trans.translation[1]],
+                None        => continue,
+            };
+
+            controller.follow(position, half_view, dt);
+
+            if let Some(focus) = controller.focus {
+                camera.eye    = [focus[0], focus[1], camera.eye[2]];
+                camera.target = [focus[0], focus[1], camera.target[2]];
+            }
+        }
+    }
+}
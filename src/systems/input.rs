@@ -0,0 +1,104 @@
+use std::sync::{Arc, Mutex};
+
+use amethyst::context::Context;
+use amethyst::context::event::{ElementState, MouseButton};
+use amethyst::ecs::{
+    RunArg,
+    Processor,
+    Entity,
+};
+use amethyst::processors::rendering::Camera;
+
+use super::viewport::Viewport;
+
+// World resource holding the mouse as of this frame
+#[derive(Clone, Debug, Default)]
+pub struct Mouse {
+    // In window pixels from the top left
+    pub position: [f32; 2],
+    // `position` in the world, as seen by the first camera
+    pub world:    Option<[f32; 2]>,
+    // Whatever has a physics body under the cursor, filled in by the physics
+    // processor. This lags a frame behind `world`.
+    pub hovered:  Option<Entity>,
+    held:         Vec<MouseButton>,
+    pressed:      Vec<MouseButton>,
+    released:     Vec<MouseButton>,
+}
+
+impl Mouse {
+    pub fn is_down(&self, button: MouseButton) -> bool {
+        self.held.contains(&button)
+    }
+
+    // Whether `button` went down this frame
+    pub fn was_pressed(&self, button: MouseButton) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    // Whether `button` came up this frame
+    pub fn was_released(&self, button: MouseButton) -> bool {
+        self.released.contains(&button)
+    }
+
+    fn button(&mut self, state: ElementState, button: MouseButton) {
+        match state {
+            ElementState::Pressed => {
+                if !self.held.contains(&button) {
+                    self.held.push(button);
+                }
+
+                self.pressed.push(button);
+            },
+            ElementState::Released => {
+                self.held.retain(|&b| b != button);
+                self.released.push(button);
+            },
+        }
+    }
+}
+
+pub struct InputProcessor;
+
+impl Processor<Arc<Mutex<Context>>> for InputProcessor {
+    fn run(&mut self, arg: RunArg, context: Arc<Mutex<Context>>) {
+        use amethyst::ecs::Join;
+        use amethyst::context::event::{EngineEvent, Event};
+
+        let (mut mouse, viewport, l_cameras) = arg.fetch(
+            |w| (
+                w.write_resource::<Mouse>(),
+                w.read_resource::<Viewport>(),
+                w.read::<Camera>(),
+            )
+        );
+        let context = context.lock().unwrap();
+
+        mouse.pressed.clear();
+        mouse.released.clear();
+
+        let engine_events = context.broadcaster.read::<EngineEvent>();
+
+        for engine_event in engine_events.iter() {
+            match engine_event.payload {
+                Event::MouseMoved(x, y) =>
+                    mouse.position = [x as f32, y as f32],
+                Event::MouseInput(state, button) =>
+                    mouse.button(state, button),
+                // Buttons released outside the window never report it
+                Event::Focused(false) => {
+                    let held = mouse.held.drain(..).collect::<Vec<_>>();
+                    mouse.released.extend(held);
+                },
+                _ => (),
+            }
+        }
+
+        // Recomputed every frame, since the camera moves under the cursor
+        mouse.world = (&l_cameras).iter().next().map(|camera| {
+            let eye = [camera.eye[0], camera.eye[1]];
+
+            viewport.screen_to_world(eye, mouse.position)
+        });
+    }
+}
@@ -1 +1,7 @@
+pub mod animation;
+pub mod brightness;
+pub mod camera;
+pub mod input;
 pub mod physics;
+pub mod reload;
+pub mod viewport;
@@ -1,7 +1,10 @@
 use std::sync::{Arc, Mutex};
 
-use nalgebra::{Matrix2, Quaternion};
-use nphysics2d::math::{Orientation, Vector};
+use nalgebra::{Matrix2, Point2, Quaternion};
+use ncollide::query::PointQuery;
+use ncollide::shape::{Ball, Cuboid};
+use ncollide::world::CollisionGroups;
+use nphysics2d::math::{Matrix, Orientation, Point, Vector};
 use nphysics2d::world::{
     World,
     RigidBodyId,
@@ -20,6 +23,8 @@ use amethyst::ecs::{
 };
 use amethyst::processors::transform::LocalTransform;
 
+use super::input::Mouse;
+
 type Precision = f32;
 
 #[derive(Default, Clone)]
@@ -32,8 +37,102 @@ impl Component for ImpulseComponent {
     type Storage = VecStorage<ImpulseComponent>;
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Collider {
+    // Full width and height
+    Box([Precision; 2]),
+    Ball(Precision),
+}
+
+// Everything needed to build a rigid body, in a form that can be read from and
+// written to files.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BodyDesc {
+    pub collider:    Collider,
+    pub dynamic:     bool,
+    pub density:     Precision,
+    pub restitution: Precision,
+    pub friction:    Precision,
+}
+
+impl Default for BodyDesc {
+    fn default() -> Self {
+        BodyDesc {
+            collider:    Collider::Box([1., 1.]),
+            dynamic:     false,
+            density:     0.5,
+            restitution: 0.5,
+            friction:    0.5,
+        }
+    }
+}
+
+impl BodyDesc {
+    pub fn build(&self) -> RigidBody<Precision> {
+        macro_rules! body {
+            ($shape:expr) => {
+                if self.dynamic {
+                    RigidBody::new_dynamic(
+                        $shape,
+                        self.density,
+                        self.restitution,
+                        self.friction,
+                    )
+                } else {
+                    RigidBody::new_static(
+                        $shape,
+                        self.restitution,
+                        self.friction,
+                    )
+                }
+            }
+        }
+
+        match self.collider {
+            Collider::Box(size) =>
+                body!(Cuboid::new(Vector::new(size[0] / 2., size[1] / 2.))),
+            Collider::Ball(radius) => body!(Ball::new(radius)),
+        }
+    }
+
+    // Point query against the collider of a body at `position`, turned by
+    // `angle` radians
+    pub fn contains(
+        &self,
+        position: [Precision; 2],
+        angle: Precision,
+        point: [Precision; 2],
+    ) -> bool {
+        let m = Matrix::new(
+            Vector::new(position[0], position[1]),
+            Orientation::new(angle),
+        );
+        let point = Point::new(point[0], point[1]);
+
+        match self.collider {
+            Collider::Box(size) =>
+                Cuboid::new(Vector::new(size[0] / 2., size[1] / 2.))
+                    .contains_point(&m, &point),
+            Collider::Ball(radius) =>
+                Ball::new(radius).contains_point(&m, &point),
+        }
+    }
+}
+
+// Rotation around z of a `LocalTransform`-style [w, x, y, z] quaternion
+pub fn quaternion_angle(q: [Precision; 4]) -> Precision {
+    2. * q[3].atan2(q[0])
+}
+
+struct Pending {
+    position: [Precision; 2],
+    angle:    Precision,
+    body:     RigidBody<Precision>,
+}
+
 pub struct PhysicsComponent {
-    handle: Result<RigidBodyId, ([Precision; 2], RigidBody<Precision>)>,
+    desc:   Option<BodyDesc>,
+    handle: Result<RigidBodyId, Pending>,
 }
 
 impl PhysicsComponent {
@@ -46,9 +145,35 @@ impl PhysicsComponent {
         pos: [Precision; 2],
     ) -> Self {
         PhysicsComponent {
-            handle: Err((pos, rgd)),
+            desc:   None,
+            handle: Err(Pending {
+                position: pos,
+                angle:    0.,
+                body:     rgd,
+            }),
+        }
+    }
+
+    // Bodies built from a description remember it, so they can be saved
+    pub fn from_desc(desc: BodyDesc, pos: [Precision; 2]) -> Self {
+        PhysicsComponent {
+            desc: Some(desc),
+            ..Self::with_position(desc.build(), pos)
         }
     }
+
+    // Starting rotation in radians, ignored once the body is in the world
+    pub fn rotated(mut self, angle: Precision) -> Self {
+        if let Err(ref mut pending) = self.handle {
+            pending.angle = angle;
+        }
+
+        self
+    }
+
+    pub fn desc(&self) -> Option<&BodyDesc> {
+        self.desc.as_ref()
+    }
 }
 
 // TODO: Is this ever safe?
@@ -59,7 +184,14 @@ impl Component for PhysicsComponent {
     type Storage = VecStorage<PhysicsComponent>;
 }
 
-pub struct PhysicsProcessor(World<Precision>);
+// World resource, while set bodies are still added, removed and synced to
+// their transforms but the simulation doesn't step and impulses are dropped
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PhysicsPaused(pub bool);
+
+// The simulation, plus the bodies that were in it last frame so the ones whose
+// entities have since been deleted can be taken out again
+pub struct PhysicsProcessor(World<Precision>, Vec<RigidBodyId>);
 
 // Shamelessly nicked from http://www.euclideanspace.com/maths/geometry/rotations/conversions/matrixToQuaternion/index.htm
 // and converted to work on 2D rotation matrices. The output quaternion is 3D
@@ -100,10 +232,28 @@ impl PhysicsProcessor {
         let mut world = World::new();
         world.set_gravity(Vector::new(0., 9.81));
 
-        PhysicsProcessor(world)
+        PhysicsProcessor(world, vec![])
     }
 }
 
+// The first rigid body whose collider contains `point`
+fn body_at(
+    world: &World<Precision>,
+    point: [Precision; 2],
+) -> Option<RigidBodyId> {
+    let point  = Point2::new(point[0], point[1]);
+    let groups = CollisionGroups::new();
+
+    world.collision_world()
+        .interferences_with_point(&point, &groups)
+        .filter_map(|object| match object.data {
+            WorldObject::RigidBody(ref body) =>
+                Some(WorldObject::rigid_body_uid(body)),
+            _ => None,
+        })
+        .next()
+}
+
 // TODO: This is only safe if we never keep handles longer than the lifetime of
 //       the run function, and even then is still unsafe if we run that function
 //       twice concurrently. At best, this is a stop-gap before converting
@@ -116,11 +266,21 @@ impl Processor<Arc<Mutex<Context>>> for PhysicsProcessor {
     fn run(&mut self, arg: RunArg, context: Arc<Mutex<Context>>) {
         use amethyst::ecs::Join;
 
-        let (mut l_physc, mut l_trans, mut l_impulses) = arg.fetch(
+        let (
+            entities,
+            mut l_physc,
+            mut l_trans,
+            mut l_impulses,
+            mut mouse,
+            paused,
+        ) = arg.fetch(
             |w| (
+                w.entities(),
                 w.write::<PhysicsComponent>(),
                 w.write::<LocalTransform>(),
                 w.write::<ImpulseComponent>(),
+                w.write_resource::<Mouse>(),
+                w.read_resource::<PhysicsPaused>().0,
             )
         );
         let ref mut wrld = self.0;
@@ -143,9 +303,15 @@ impl Processor<Arc<Mutex<Context>>> for PhysicsProcessor {
 
                         continue;
                     }
-                    Err((ref pos, ref rgd)) => {
-                        let mut rigid_body = rgd.clone();
+                    Err(ref pending) => {
+                        let mut rigid_body = pending.body.clone();
 
+                        if pending.angle != 0. {
+                            rigid_body.append_rotation(
+                                &Orientation::new(pending.angle)
+                            );
+                        }
+                        let pos = &pending.position;
                         rigid_body.append_translation(pos.into());
 
                         WorldObject::rigid_body_uid(
@@ -157,6 +323,35 @@ impl Processor<Arc<Mutex<Context>>> for PhysicsProcessor {
             phys.handle = Ok(uid);
         }
 
+        let live = (&l_physc).iter()
+            .filter_map(|phys| phys.handle.as_ref().ok().cloned())
+            .collect::<Vec<_>>();
+
+        for uid in self.1.iter().filter(|uid| !live.contains(uid)) {
+            if let Some(handle) = wrld.get_rigid_body_by_uid(uid).cloned() {
+                wrld.remove_rigid_body(&handle);
+            }
+        }
+
+        self.1 = live;
+
+        let hovered = mouse.world.and_then(|point| body_at(wrld, point));
+
+        mouse.hovered = hovered.and_then(
+            |uid| (&entities, &l_physc).iter()
+                .find(|&(_, phys)| phys.handle.as_ref().ok() == Some(&uid))
+                .map(|(entity, _)| entity)
+        );
+
+        if paused {
+            for impls in (&mut l_impulses).iter() {
+                impls.angular = None;
+                impls.linear  = None;
+            }
+
+            return;
+        }
+
         for (phys, impls) in (&mut l_physc, &mut l_impulses).iter() {
             if let Some(handle) = phys.handle.as_ref().ok()
                 .and_then(|uid| wrld.get_rigid_body_by_uid(&uid))
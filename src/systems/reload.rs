@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use amethyst::context::Context;
+use amethyst::context::asset_manager::{
+    Asset,
+    AssetLoader,
+    AssetManager,
+    Texture,
+};
+use amethyst::ecs::{
+    RunArg,
+    Processor,
+};
+use amethyst::processors::rendering::Renderable;
+
+use loaders::{GltfLoader, GltfScene, LoaderError, ObjObjects, Parse};
+use loaders::{Dependencies, ObjLoader, Prefab, SpriteSheet};
+use loaders::{error, split_file};
+use loaders::obj::MtlLib;
+
+// Polls the files under every asset directory and reloads any asset that was
+// already loaded when its file changes on disk. Renderables refer to meshes and
+// textures by name, so re-adding an asset under the same name is enough to swap
+// it in everywhere.
+pub struct HotReloadProcessor {
+    // Highest priority first, as returned by `AssetPaths::layers`
+    roots:    Vec<PathBuf>,
+    interval: Duration,
+    last:     Instant,
+    // Every file under the roots, with its name relative to its root
+    files:    HashMap<PathBuf, (String, SystemTime)>,
+    // The material libraries of every loaded obj, by the obj's file name, so
+    // a changed .mtl only reloads the objs that use it
+    mtllibs:  HashMap<String, Vec<String>>,
+}
+
+impl HotReloadProcessor {
+    pub fn new(roots: Vec<PathBuf>) -> Self {
+        let mut out = HotReloadProcessor {
+            roots:    roots,
+            interval: Duration::from_millis(500),
+            last:     Instant::now(),
+            files:    HashMap::new(),
+            mtllibs:  HashMap::new(),
+        };
+
+        out.changed_files();
+
+        out
+    }
+
+    // Returns the names of the files that changed since the last call, like
+    // `models/deer.obj`, which is also what the asset store calls them.
+    fn changed_files(&mut self) -> Vec<String> {
+        let mut paths = vec![];
+
+        for root in &self.roots {
+            walk(root, root, &mut paths);
+        }
+
+        let mut changed = vec![];
+
+        for (file, path) in paths {
+            let mtime = match fs::metadata(&path).and_then(|m| m.modified()) {
+                Ok(mtime) => mtime,
+                Err(_)    => continue,
+            };
+
+            if self.files.insert(path, (file.clone(), mtime))
+                .map(|(_, old)| old != mtime)
+                .unwrap_or(false)
+            {
+                changed.push(file);
+            }
+        }
+
+        changed
+    }
+
+    // The file that the asset store would actually read for `file_name`,
+    // which may be in a higher layer than the one that changed.
+    fn resolve(&self, file_name: &str) -> Option<PathBuf> {
+        self.roots.iter()
+            .map(|r| r.join(file_name))
+            .find(|p| p.is_file())
+    }
+
+    // Reads the material libraries of every obj that was loaded since the last
+    // poll. Reloaded objs are recorded again by `reload_object`.
+    fn record_mtllibs(&mut self, assets: &AssetManager) {
+        let loaded = self.files.values()
+            .map(|&(ref file, _)| file)
+            .filter(|file| !self.mtllibs.contains_key(*file))
+            .filter(|file| match split_file(file, "") {
+                (name, "obj") => assets.id_from_name(name).is_some(),
+                _             => false,
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for file in loaded {
+            let libs = self.resolve(&file)
+                .ok_or_else(|| LoaderError::missing(&file, "obj"))
+                .and_then(|path| mtllibs(&path, &file));
+
+            // Recorded either way, so a broken obj is only reported once
+            let libs = match libs {
+                Ok(libs) => libs,
+                Err(e)   => {
+                    error::report::<()>(&file, e);
+                    vec![]
+                },
+            };
+
+            self.mtllibs.insert(file, libs);
+        }
+    }
+
+    // The loaded objs that use the material library `mtl`
+    fn dependents(&self, mtl: &str) -> Vec<String> {
+        self.mtllibs.iter()
+            .filter(|&(_, libs)| libs.iter().any(|lib| lib == mtl))
+            .map(|(obj, _)| obj.clone())
+            .collect()
+    }
+}
+
+// Collects every file under `dir`, named relative to `root` with `/` between
+// directories, the same way asset packs name them
+fn walk(root: &Path, dir: &Path, out: &mut Vec<(String, PathBuf)>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_)      => return,
+    };
+
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        if path.is_dir() {
+            walk(root, &path, out);
+        } else if let Ok(rel) = path.strip_prefix(root) {
+            let file = rel.components()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            out.push((file, path.clone()));
+        }
+    }
+}
+
+fn mtllibs(path: &Path, file: &str) -> Result<Vec<String>, LoaderError> {
+    let (name, ext) = split_file(file, "obj");
+
+    Ok(ObjLoader::parse(&read(path, name, ext)?)?.dependencies())
+}
+
+fn read(path: &Path, name: &str, ext: &str) -> Result<Vec<u8>, LoaderError> {
+    let mut buf = vec![];
+
+    File::open(path)
+        .and_then(|mut f| f.read_to_end(&mut buf))
+        .map_err(|_| LoaderError::missing(name, ext))?;
+
+    Ok(buf)
+}
+
+fn reload<A>(
+    assets: &mut AssetManager,
+    path: &Path,
+    name: &str,
+    ext: &str,
+) -> Result<(), LoaderError>
+    where A: ::std::any::Any + Send + Sync
+{
+    let buf = read(path, name, ext)?;
+
+    error::take_last_error();
+
+    match assets.load_asset_from_raw::<A>(name, ext, &buf) {
+        Some(_) => Ok(()),
+        None    => Err(
+            error::take_last_error()
+                .unwrap_or_else(|| LoaderError::missing(name, ext))
+        ),
+    }
+}
+
+// For loaders that need to know the file's name, see `Parse::parse_named`
+fn reload_named<A, L>(
+    assets: &mut AssetManager,
+    path: &Path,
+    name: &str,
+    ext: &str,
+) -> Result<(), LoaderError>
+    where A: ::std::any::Any + Send + Sync,
+          L: AssetLoader<A> + Parse
+{
+    let loader = L::parse_named(name, &read(path, name, ext)?)?;
+
+    error::take_last_error();
+
+    match assets.load_asset_from_data::<A, L>(name, loader) {
+        Some(_) => Ok(()),
+        None    => Err(
+            error::take_last_error()
+                .unwrap_or_else(|| LoaderError::Upload(name.into()))
+        ),
+    }
+}
+
+impl HotReloadProcessor {
+    // Also records the obj's material libraries again, in case they changed
+    fn reload_object(
+        &mut self,
+        assets: &mut AssetManager,
+        path: &Path,
+        file: &str,
+        renderables: &mut HashMap<String, Renderable>,
+    ) -> Result<(), LoaderError> {
+        let (name, _) = split_file(file, "obj");
+
+        reload::<ObjObjects>(assets, path, name, "obj")?;
+        self.mtllibs.insert(file.into(), mtllibs(path, file)?);
+
+        if let Some(id) = assets.id_from_name(name) {
+            let objects = assets.read_assets();
+            let objects: Option<&Asset<ObjObjects>> = objects.get(id);
+
+            if let Some(objects) = objects {
+                renderables.extend(
+                    objects.0.values()
+                        .map(|o| {
+                            (o.renderable.mesh.clone(), o.renderable.clone())
+                        })
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    // Materials are baked into the objects that use them
+    fn reload_material(
+        &mut self,
+        assets: &mut AssetManager,
+        path: &Path,
+        file: &str,
+        renderables: &mut HashMap<String, Renderable>,
+    ) -> Result<(), LoaderError> {
+        let (name, ext) = split_file(file, "mtl");
+
+        reload::<MtlLib>(assets, path, name, ext)?;
+
+        for obj in self.dependents(file) {
+            if let Some(obj_path) = self.resolve(&obj) {
+                self.reload_object(assets, &obj_path, &obj, renderables)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Processor<Arc<Mutex<Context>>> for HotReloadProcessor {
+    fn run(&mut self, arg: RunArg, context: Arc<Mutex<Context>>) {
+        use amethyst::ecs::Join;
+
+        let mut l_rends = arg.fetch(|w| w.write::<Renderable>());
+
+        if self.last.elapsed() < self.interval {
+            return;
+        }
+        self.last = Instant::now();
+
+        let changed = self.changed_files();
+
+        let mut context = context.lock().unwrap();
+        let assets      = &mut context.asset_manager;
+
+        self.record_mtllibs(assets);
+
+        // Fresh renderables keyed by mesh name, used to patch up components
+        // whose material changed along with the model.
+        let mut updated = HashMap::new();
+
+        for file in changed {
+            let (name, ext) = match split_file(&file, "") {
+                // No extension, or a dot in a directory name
+                (_, "")                       => continue,
+                (_, ext) if ext.contains('/') => continue,
+                (name, ext)                   => (name, ext),
+            };
+
+            let path = match self.resolve(&file) {
+                Some(path) => path,
+                None       => continue,
+            };
+
+            if assets.id_from_name(name).is_none() {
+                continue;
+            }
+
+            let result = match ext {
+                "png" | "jpg" | "jpeg" | "tga" | "bmp" | "gif" | "dds" =>
+                    reload::<Texture>(assets, &path, name, ext),
+                "sheet" =>
+                    reload::<SpriteSheet>(assets, &path, name, ext),
+                // Only affects prefabs spawned from now on
+                "prefab" =>
+                    reload::<Prefab>(assets, &path, name, ext),
+                "gltf" | "glb" =>
+                    reload_named::<GltfScene, GltfLoader>(
+                        assets,
+                        &path,
+                        name,
+                        ext,
+                    ),
+                "obj" =>
+                    self.reload_object(assets, &path, &file, &mut updated),
+                "mtl" =>
+                    self.reload_material(assets, &path, &file, &mut updated),
+                _ => continue,
+            };
+
+            match result {
+                Ok(()) => {
+                    let _ = writeln!(io::stderr(), "[reload] {}", file);
+                },
+                Err(e) => {
+                    let _ = writeln!(
+                        io::stderr(),
+                        "[reload] {}: {}",
+                        file,
+                        e
+                    );
+                },
+            }
+        }
+
+        for rend in (&mut l_rends).iter() {
+            if let Some(fresh) = updated.get(&rend.mesh) {
+                *rend = fresh.clone();
+            }
+        }
+    }
+}
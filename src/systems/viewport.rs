@@ -0,0 +1,220 @@
+use std::sync::{Arc, Mutex};
+
+use amethyst::context::Context;
+use amethyst::ecs::{
+    RunArg,
+    Processor,
+    Entity,
+};
+use amethyst::processors::rendering::{Camera, Projection};
+use amethyst::processors::transform::Transform;
+
+// How far in front of the camera letterbox bars are drawn
+const BAR_DEPTH: f32 = 0.01;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scaling {
+    // Keeps the virtual height and shows as much width as the window has
+    Expand,
+    // Scales the virtual screen as large as it fits, with bars over the rest
+    Letterbox,
+    // As `Letterbox`, but only by whole numbers so every virtual pixel is the
+    // same size on screen
+    PixelPerfect,
+}
+
+// World resource mapping a fixed virtual resolution onto the window. The world
+// is y-down, with `pixels_per_unit` virtual pixels to one world unit, which is
+// one tile in maps.
+#[derive(Clone, Debug)]
+pub struct Viewport {
+    pub resolution:      [u32; 2],
+    pub pixels_per_unit: f32,
+    pub scaling:         Scaling,
+    window:              [u32; 2],
+    // Window pixels per virtual pixel
+    scale:               f32,
+    // Created by `states::create_camera` and positioned by the processor
+    pub bars:            Vec<Entity>,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Viewport::new([320, 200], 100., Scaling::Letterbox)
+    }
+}
+
+impl Viewport {
+    pub fn new(
+        resolution: [u32; 2],
+        pixels_per_unit: f32,
+        scaling: Scaling,
+    ) -> Self {
+        let mut viewport = Viewport {
+            resolution:      resolution,
+            pixels_per_unit: pixels_per_unit,
+            scaling:         scaling,
+            window:          resolution,
+            scale:           1.,
+            bars:            vec![],
+        };
+
+        viewport.resize(resolution);
+        viewport
+    }
+
+    pub fn window(&self) -> [u32; 2] {
+        self.window
+    }
+
+    pub fn resize(&mut self, window: [u32; 2]) {
+        // Minimised windows report zero, which would divide by zero below
+        self.window = [window[0].max(1), window[1].max(1)];
+
+        let fit = [
+            self.window[0] as f32 / self.resolution[0] as f32,
+            self.window[1] as f32 / self.resolution[1] as f32,
+        ];
+
+        self.scale = match self.scaling {
+            Scaling::Expand       => fit[1],
+            Scaling::Letterbox    => fit[0].min(fit[1]),
+            Scaling::PixelPerfect => fit[0].min(fit[1]).floor().max(1.),
+        };
+    }
+
+    // Half the window's size in world units
+    pub fn half_window(&self) -> [f32; 2] {
+        let per_unit = self.scale * self.pixels_per_unit;
+
+        [
+            self.window[0] as f32 / per_unit / 2.,
+            self.window[1] as f32 / per_unit / 2.,
+        ]
+    }
+
+    // Half the size of the part of the world that isn't behind a bar
+    pub fn half_view(&self) -> [f32; 2] {
+        let window = self.half_window();
+
+        if self.scaling == Scaling::Expand {
+            return window;
+        }
+
+        [
+            (self.resolution[0] as f32 / self.pixels_per_unit / 2.)
+                .min(window[0]),
+            (self.resolution[1] as f32 / self.pixels_per_unit / 2.)
+                .min(window[1]),
+        ]
+    }
+
+    // Covers the whole window, centred on the camera. Top is negative since
+    // the world is y-down, which goes with an up vector of +y.
+    pub fn projection(&self) -> Projection {
+        let half = self.half_window();
+
+        Projection::Orthographic {
+            left:   -half[0],
+            right:   half[0],
+            bottom:  half[1],
+            top:    -half[1],
+            near:    0.0,
+            far:     1.0,
+        }
+    }
+
+    // `pos` is in window pixels from the top left, `eye` the camera position
+    pub fn screen_to_world(&self, eye: [f32; 2], pos: [f32; 2]) -> [f32; 2] {
+        let per_unit = self.scale * self.pixels_per_unit;
+
+        [
+            eye[0] + (pos[0] - self.window[0] as f32 / 2.) / per_unit,
+            eye[1] + (pos[1] - self.window[1] as f32 / 2.) / per_unit,
+        ]
+    }
+
+    pub fn world_to_screen(&self, eye: [f32; 2], pos: [f32; 2]) -> [f32; 2] {
+        let per_unit = self.scale * self.pixels_per_unit;
+
+        [
+            (pos[0] - eye[0]) * per_unit + self.window[0] as f32 / 2.,
+            (pos[1] - eye[1]) * per_unit + self.window[1] as f32 / 2.,
+        ]
+    }
+
+    // Centre and size of the left, right, top and bottom bars
+    fn bar_rects(&self, eye: [f32; 2]) -> [([f32; 2], [f32; 2]); 4] {
+        let window = self.half_window();
+        let view   = self.half_view();
+
+        let side = [window[0] - view[0], window[1] * 2.];
+        let edge = [window[0] * 2., window[1] - view[1]];
+        let x    = (window[0] + view[0]) / 2.;
+        let y    = (window[1] + view[1]) / 2.;
+
+        [
+            ([eye[0] - x, eye[1]], side),
+            ([eye[0] + x, eye[1]], side),
+            ([eye[0], eye[1] - y], edge),
+            ([eye[0], eye[1] + y], edge),
+        ]
+    }
+}
+
+// Keeps the projection in step with the window and the letterbox bars in front
+// of the camera.
+pub struct ViewportProcessor;
+
+impl Processor<Arc<Mutex<Context>>> for ViewportProcessor {
+    fn run(&mut self, arg: RunArg, context: Arc<Mutex<Context>>) {
+        use amethyst::ecs::Join;
+        use amethyst::context::event::{EngineEvent, Event};
+
+        let (mut viewport, mut projection, mut l_cameras, mut l_globals) =
+            arg.fetch(|w| (
+                w.write_resource::<Viewport>(),
+                w.write_resource::<Projection>(),
+                w.write::<Camera>(),
+                w.write::<Transform>(),
+            ));
+        let context = context.lock().unwrap();
+
+        let engine_events = context.broadcaster.read::<EngineEvent>();
+        let resized = engine_events.iter()
+            .filter_map(|e| match e.payload {
+                Event::Resized(w, h) => Some([w, h]),
+                _                    => None,
+            })
+            .last();
+
+        if let Some(window) = resized {
+            viewport.resize(window);
+            *projection = viewport.projection();
+
+            for camera in (&mut l_cameras).iter() {
+                camera.projection = *projection;
+            }
+        }
+
+        let eye = match (&l_cameras).iter().next() {
+            Some(camera) => camera.eye,
+            None         => return,
+        };
+
+        let rects = viewport.bar_rects([eye[0], eye[1]]);
+
+        for (&bar, &(centre, size)) in viewport.bars.iter().zip(&rects) {
+            if let Some(global) = l_globals.get_mut(bar) {
+                // Bars have no `LocalTransform`, so the transform processor
+                // leaves this alone
+                *global = Transform([
+                    [size[0].max(0.), 0., 0., 0.],
+                    [0., size[1].max(0.), 0., 0.],
+                    [0., 0., 1., 0.],
+                    [centre[0], centre[1], eye[2] - BAR_DEPTH, 1.],
+                ]);
+            }
+        }
+    }
+}
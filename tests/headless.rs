@@ -0,0 +1,46 @@
+// Runs the game binary the way CI does, without a window
+
+use std::env;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+// Cargo builds the binary next to the `deps` directory tests are built in
+fn game() -> PathBuf {
+    let mut dir = env::current_exe().unwrap();
+    dir.pop();
+
+    if dir.ends_with("deps") {
+        dir.pop();
+    }
+
+    dir.join(format!("hunting_game{}", env::consts::EXE_SUFFIX))
+}
+
+fn run(args: &[&str]) -> Output {
+    Command::new(game())
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn frame_limit() {
+    let out    = run(&["--headless", "--frames", "60"]);
+    let stdout = String::from_utf8_lossy(&out.stdout);
+
+    assert!(
+        out.status.success(),
+        "{}\n{}",
+        out.status,
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(stdout.contains("Ran 60 frames"), "{}", stdout);
+}
+
+#[test]
+fn missing_level() {
+    let out = run(&["--headless", "--frames", "60", "--level", "missing.tmx"]);
+
+    assert!(!out.status.success());
+}